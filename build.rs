@@ -1,7 +1,9 @@
+use std::collections::hash_map::DefaultHasher;
 use std::env;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{Cursor, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn brotli_compress(data: &[u8]) -> Vec<u8> {
     let mut out = Vec::new();
@@ -20,6 +22,79 @@ fn gzip_compress(data: &[u8]) -> Vec<u8> {
     enc.finish().expect("gzip finish failed")
 }
 
+/// Decode standard base64 (with `=` padding), as found in the icons' data URIs.
+fn base64_decode(s: &str) -> Vec<u8> {
+    let val = |c: u8| match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => panic!("bad base64 byte {c:#x}"),
+    };
+    let digits: Vec<u8> = s.bytes().filter(|&c| c != b'=' && !c.is_ascii_whitespace()).map(val).collect();
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &d)| n | (d as u32) << (18 - 6 * i));
+        out.extend_from_slice(&n.to_be_bytes()[1..chunk.len()]);
+    }
+    out
+}
+
+fn content_hash(data: &[u8]) -> String {
+    let mut h = DefaultHasher::new();
+    data.hash(&mut h);
+    format!("{:016x}", h.finish())
+}
+
+/// Service icons, served from `/icons/<key>`. SVGs are precompressed like the CSS/JS;
+/// the `.html` icons are `<img>` tags with a base64 PNG, decoded here to the raw PNG
+/// (already compressed, so served as-is). Generates `icons.rs`, included by lib.rs.
+fn build_icons(out_dir: &Path) {
+    const ICONS: &[(&str, &str)] = &[
+        ("google", "google.svg"),
+        ("bing", "bing.svg"),
+        ("heanet", "heanet.html"),
+        ("digiweb", "digiweb.html"),
+        ("dkit", "dkit.svg"),
+        ("youtube", "youtube.html"),
+        ("outlook", "outlook.html"),
+        ("whatsapp", "whatsapp.svg"),
+        ("cloudflare", "cloudflare.svg"),
+        ("quad9", "quad9.svg"),
+        ("dns", "dns.svg"),
+        ("fallback", "fallback.svg"),
+    ];
+    let icon_dir = out_dir.join("icons");
+    fs::create_dir_all(&icon_dir).expect("create icons dir");
+    let mut table = String::from("pub const ICONS: &[Icon] = &[\n");
+    for (key, file) in ICONS {
+        let src = format!("src/icons/{file}");
+        println!("cargo:rerun-if-changed={src}");
+        let path = |ext: &str| icon_dir.join(format!("{key}.{ext}")).display().to_string();
+        let (content_type, raw, compressed) = if file.ends_with(".svg") {
+            let raw = fs::read(&src).expect("missing icon");
+            fs::write(path("svg.br"), brotli_compress(&raw)).expect("write icon.br");
+            fs::write(path("svg.gz"), gzip_compress(&raw)).expect("write icon.gz");
+            let compressed = format!("Some((include_bytes!({:?}), include_bytes!({:?})))", path("svg.br"), path("svg.gz"));
+            ("image/svg+xml", raw, compressed)
+        } else {
+            let html = fs::read_to_string(&src).expect("missing icon");
+            let data = html.split("base64,").nth(1).and_then(|rest| rest.split('"').next())
+                .unwrap_or_else(|| panic!("{src}: no base64 data URI"));
+            ("image/png", base64_decode(data), "None".to_string())
+        };
+        let ext = if content_type == "image/png" { "png" } else { "svg" };
+        fs::write(path(ext), &raw).expect("write icon");
+        table.push_str(&format!(
+            "    Icon {{ key: {key:?}, content_type: {content_type:?}, hash: {:?}, raw: include_bytes!({:?}), compressed: {compressed} }},\n",
+            content_hash(&raw), path(ext),
+        ));
+    }
+    table.push_str("];\n");
+    fs::write(out_dir.join("icons.rs"), table).expect("write icons.rs");
+}
+
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
@@ -39,4 +114,6 @@ fn main() {
     println!("cargo:rerun-if-changed=web/dist/tokens.css");
     println!("cargo:rerun-if-changed=src/app.css");
     println!("cargo:rerun-if-changed=src/app.js");
+
+    build_icons(&out_dir);
 }
@@ -3,9 +3,12 @@ function saveState(){
     var sc=Array.from(document.querySelectorAll('.svc-card[open]')).map(function(d){return d.dataset.title}).filter(Boolean).join('|');
     var si=Array.from(document.querySelectorAll('.svc-item[open]')).map(function(d){return d.dataset.svc}).filter(Boolean).join('|');
     var th=document.documentElement.dataset.theme||'';
-    document.cookie='pg=ho='+ho+'&sc='+sc+'&si='+si+'&th='+th+'; path=/; SameSite=Strict';
+    var ss=document.querySelector('.services-section');
+    var sv=(ss&&!ss.open)?'0':'1';
+    var cb=('cb' in document.documentElement.dataset)?'1':'0';
+    document.cookie='pg=ho='+ho+'&sc='+sc+'&si='+si+'&th='+th+'&sv='+sv+'&cb='+cb+'; path=/; SameSite=Strict';
 }
-document.querySelectorAll('.host-card,.svc-card,.svc-item').forEach(function(el){
+document.querySelectorAll('.host-card,.svc-card,.svc-item,.services-section').forEach(function(el){
     el.addEventListener('toggle',saveState);
 });
 (function(){
@@ -24,3 +27,84 @@ document.querySelectorAll('.host-card,.svc-card,.svc-item').forEach(function(el)
         saveState();
     });
 }());
+(function(){
+    var btn=document.getElementById('cb-btn');
+    if(!btn)return;
+    var root=document.documentElement;
+    function show(){
+        var on='cb' in root.dataset;
+        btn.textContent='◑';
+        btn.title=on?'Colour-blind cues: on':'Colour-blind cues: off';
+        btn.setAttribute('aria-pressed',on?'true':'false');
+    }
+    show();
+    btn.addEventListener('click',function(){
+        if('cb' in root.dataset){delete root.dataset.cb;}else{root.dataset.cb='';}
+        show();
+        saveState();
+    });
+}());
+(function(){
+    // Meta-refresh reloads jump to the top; carry the scroll offset across in
+    // sessionStorage. Throws (private mode, disabled storage) just mean no restore.
+    var key='pg-scroll';
+    try{
+        var y=sessionStorage.getItem(key);
+        if(y!==null){window.scrollTo(0,parseInt(y,10)||0);}
+        window.addEventListener('pagehide',function(){
+            try{sessionStorage.setItem(key,String(window.scrollY));}catch(e){}
+        });
+    }catch(e){}
+}());
+(function(){
+    // Live updates: /events announces each poll round; fetch the re-rendered page
+    // and swap each card's contents in place, so open cards, scroll and unchanged
+    // sparklines stay put. The <noscript> meta refresh covers script-less clients;
+    // if no round is announced for two refresh intervals (stream blocked by a proxy,
+    // server restarted), fall back to a full reload.
+    if(!window.EventSource||!window.fetch||!window.DOMParser)return;
+    var sel='details[data-addr],details[data-title],details[data-svc]';
+    function id(el){
+        var a=el.hasAttribute('data-addr')?'data-addr':el.hasAttribute('data-svc')?'data-svc':'data-title';
+        return a+'='+el.getAttribute(a);
+    }
+    function body(el){
+        return Array.from(el.children).filter(function(c){return c.tagName!=='SUMMARY'});
+    }
+    function html(nodes){return nodes.map(function(n){return n.outerHTML}).join('')}
+    function patch(doc){
+        var fresh={};
+        doc.querySelectorAll(sel).forEach(function(el){fresh[id(el)]=el;});
+        document.querySelectorAll(sel).forEach(function(el){
+            var f=fresh[id(el)];
+            if(!f)return;
+            var s=el.querySelector('summary'),fs=f.querySelector('summary');
+            if(s&&fs&&s.innerHTML!==fs.innerHTML)s.innerHTML=fs.innerHTML;
+            // Cards holding other cards (service groups) only swap their header.
+            if(el.querySelector(sel))return;
+            var old=body(el),next=body(f);
+            if(html(old)===html(next))return;
+            old.forEach(function(c){c.remove()});
+            next.forEach(function(c){el.appendChild(document.importNode(c,true))});
+        });
+        var icon=document.querySelector('link[type="image/svg+xml"]'),ficon=doc.querySelector('link[type="image/svg+xml"]');
+        if(icon&&ficon&&icon.getAttribute('href')!==ficon.getAttribute('href'))icon.setAttribute('href',ficon.getAttribute('href'));
+    }
+    var busy=false,timer;
+    var wait=(parseInt(document.documentElement.getAttribute('data-refresh'),10)||60)*2000;
+    function arm(){
+        clearTimeout(timer);
+        timer=setTimeout(function(){location.reload()},wait);
+    }
+    arm();
+    new EventSource('/events').addEventListener('round',function(){
+        arm();
+        if(busy)return;
+        busy=true;
+        fetch('/',{credentials:'same-origin'})
+            .then(function(r){return r.ok?r.text():null})
+            .then(function(t){if(t)patch(new DOMParser().parseFromString(t,'text/html'))})
+            .catch(function(){})
+            .then(function(){busy=false});
+    });
+}());
@@ -4,87 +4,77 @@ use chrono::NaiveDateTime;
 use rusqlite::{Connection, OpenFlags};
 
 use pi_glass::*;
-
-/// Returns seconds until the next occurrence of "HH:MM" in local time.
-fn secs_until(hh_mm: &str) -> u64 {
-    let now: NaiveDateTime = chrono::Local::now().naive_local();
-    let mut it = hh_mm.splitn(2, ':');
-    let h: u32 = it.next().and_then(|s| s.parse().ok()).unwrap_or(8);
-    let m: u32 = it.next().and_then(|s| s.parse().ok()).unwrap_or(0);
-
-    let today_at = now
-        .date()
-        .and_hms_opt(h, m, 0)
-        .unwrap_or_else(|| now.date().and_hms_opt(8, 0, 0).unwrap());
-
-    let target = if now < today_at {
-        today_at
-    } else {
-        today_at + chrono::Duration::days(1)
-    };
-
-    (target - now).num_seconds().max(0) as u64
-}
-
-async fn send_mailgun(cfg: &MailerConfig, html: &str) -> Result<(), reqwest::Error> {
-    let url = format!("https://api.mailgun.net/v3/{}/messages", cfg.mailgun_domain);
-    let client = reqwest::Client::new();
-
-    let mut form = reqwest::multipart::Form::new()
-        .text("from",    cfg.from.clone())
-        .text("subject", cfg.subject.clone())
-        .text("html",    html.to_string());
-
-    for recipient in &cfg.to {
-        form = form.text("to", recipient.clone());
+use tracing::{error, info, warn};
+
+/// Returns the time until the next occurrence of any of the "HH:MM" times on the
+/// wall clock `now`. Unparseable entries are skipped (validation reports them); none
+/// left = 08:00.
+fn time_until_any(times: &[String], now: NaiveDateTime) -> Duration {
+    let mut ats: Vec<_> = times.iter().filter_map(|t| parse_hh_mm(t)).collect();
+    if ats.is_empty() {
+        ats.push(chrono::NaiveTime::from_hms_opt(8, 0, 0).unwrap());
     }
 
-    let resp = client
-        .post(&url)
-        .basic_auth("api", Some(&cfg.mailgun_api_key))
-        .multipart(form)
-        .send()
-        .await?;
-
-    if resp.status().is_success() {
-        eprintln!("pi-glass-mailer: sent to {}", cfg.to.join(", "));
-    } else {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        eprintln!("pi-glass-mailer: mailgun error {status}: {body}");
-    }
-
-    Ok(())
+    let target = ats.into_iter()
+        .map(|at| {
+            let today_at = now.date().and_time(at);
+            if now < today_at { today_at } else { today_at + chrono::Duration::days(1) }
+        })
+        .min()
+        .unwrap();
+
+    // Not rounded to whole seconds: waking early would find the same minute still ahead
+    // and send again.
+    (target - now).to_std().unwrap_or_default()
 }
 
 #[tokio::main]
 async fn main() {
-    let (config, _) = load_config();
+    init_logging();
+    let (config, _) = load_config().unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    });
+    let problems = config.validate();
+    for problem in &problems {
+        warn!("Config: {problem}");
+    }
+    if !problems.is_empty() && config.is_strict() {
+        std::process::exit(1);
+    }
     let mcfg = config
         .mailer
         .as_ref()
         .expect("pi-glass-mailer requires a [mailer] section in config.toml");
 
-    eprintln!("pi-glass-mailer: will send daily at {} to {}", mcfg.send_at, mcfg.to.join(", "));
+    info!("will send daily at {} to {}", mcfg.send_at.join(", "), mcfg.to.join(", "));
 
     loop {
-        let secs = secs_until(&mcfg.send_at);
-        eprintln!("pi-glass-mailer: next send in {}m", secs / 60);
-        tokio::time::sleep(Duration::from_secs(secs)).await;
+        let wait = time_until_any(&mcfg.send_at, config.local_now());
+        info!("next send in {}m", wait.as_secs() / 60);
+        tokio::time::sleep(wait).await;
 
         let db = match Connection::open_with_flags(
             &config.db_path,
             OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
         ) {
             Ok(db) => db,
-            Err(e) => { eprintln!("pi-glass-mailer: db error: {e}"); continue; }
+            Err(e) => { error!("db error: {e}"); continue; }
         };
 
+        if mcfg.send_only_on_incident && !any_down_since(&db, 24 * 60) {
+            info!("nothing to report, skipping");
+            continue;
+        }
+
         let html = render_full_page(&db, &config);
         let html = inline_css_vars(html);
 
-        if let Err(e) = send_mailgun(mcfg, &html).await {
-            eprintln!("pi-glass-mailer: send error: {e}");
+        let csv = mcfg.attach_csv.then(|| query_samples_csv(&db, 24 * 60));
+        let filename = format!("pi-glass-{}.csv", config.local_now().format("%Y-%m-%d"));
+        match send_mail(mcfg, &mcfg.subject, &html, csv.as_deref().map(|c| (filename.as_str(), c))).await {
+            Ok(()) => info!("sent to {}", mcfg.to.join(", ")),
+            Err(e) => error!("send error: {e}"),
         }
     }
 }
@@ -0,0 +1,44 @@
+//! Graphite plaintext push. After each round the poll loop hands over its rows and
+//! they're written as `prefix.<target>.latency_ms value ts` / `prefix.<target>.up 1|0`
+//! lines over one short-lived TCP connection, on its own task — a slow or absent
+//! carbon server never delays polling, and a failed push is logged and dropped.
+
+use std::time::Duration;
+
+use pi_glass::GraphiteConfig;
+use tokio::io::AsyncWriteExt;
+
+/// DB key → Graphite-safe path segment: "svc:Google DNS" → "svc_Google_DNS",
+/// "192.168.1.1" → "192_168_1_1". Dots would split the path; colons and spaces
+/// aren't allowed by carbon.
+fn metric_name(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// `rows` are the round's (key, status, latency_ms); `ts` is the round's unix time.
+pub fn push(cfg: &GraphiteConfig, rows: &[(String, &'static str, Option<f64>)], ts: i64) {
+    let prefix = cfg.prefix.trim_end_matches('.');
+    let mut body = String::new();
+    for (key, status, latency_ms) in rows {
+        let name = metric_name(key);
+        body.push_str(&format!("{prefix}.{name}.up {} {ts}\n", u8::from(*status == "UP")));
+        if let Some(ms) = latency_ms {
+            body.push_str(&format!("{prefix}.{name}.latency_ms {ms:.3} {ts}\n"));
+        }
+    }
+    let addr = format!("{}:{}", cfg.host, cfg.port);
+    tokio::spawn(async move {
+        let res = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut stream = tokio::net::TcpStream::connect(&addr).await?;
+            stream.write_all(body.as_bytes()).await?;
+            stream.shutdown().await
+        }).await;
+        match res {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("push to {addr} failed: {e}"),
+            Err(_) => tracing::warn!("push to {addr} timed out"),
+        }
+    });
+}
@@ -1,6 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write;
-use chrono::Local;
+use std::net::IpAddr;
+use chrono::{Local, TimeZone};
 use rusqlite::{params, Connection};
 use serde::Deserialize;
 
@@ -11,6 +12,8 @@ pub struct UiCookie {
     pub open_svc_cards: Option<HashSet<String>>,
     pub open_svc_items: Option<HashSet<String>>,
     pub theme: Option<String>,  // None = auto, Some("dark") or Some("light")
+    pub services_open: bool,    // whole services section; open unless the cookie says sv=0
+    pub colorblind: bool,       // cb=1: shape/pattern cues on top of status colours
 }
 
 pub fn parse_ui_cookie(cookie_str: &str) -> UiCookie {
@@ -20,13 +23,15 @@ pub fn parse_ui_cookie(cookie_str: &str) -> UiCookie {
         .unwrap_or("");
 
     if pg.is_empty() {
-        return UiCookie { open_hosts: None, open_svc_cards: None, open_svc_items: None, theme: None };
+        return UiCookie { open_hosts: None, open_svc_cards: None, open_svc_items: None, theme: None, services_open: true, colorblind: false };
     }
 
     let mut open_hosts = None;
     let mut open_svc_cards = None;
     let mut open_svc_items = None;
     let mut theme = None;
+    let mut services_open = true;
+    let mut colorblind = false;
 
     for field in pg.split('&') {
         if let Some(v) = field.strip_prefix("ho=") {
@@ -37,10 +42,14 @@ pub fn parse_ui_cookie(cookie_str: &str) -> UiCookie {
             open_svc_items = Some(v.split('|').filter(|s| !s.is_empty()).map(String::from).collect());
         } else if let Some(v) = field.strip_prefix("th=") {
             if v == "dark" || v == "light" { theme = Some(v.to_string()); }
+        } else if let Some(v) = field.strip_prefix("sv=") {
+            services_open = v != "0";
+        } else if let Some(v) = field.strip_prefix("cb=") {
+            colorblind = v == "1";
         }
     }
 
-    UiCookie { open_hosts, open_svc_cards, open_svc_items, theme }
+    UiCookie { open_hosts, open_svc_cards, open_svc_items, theme, services_open, colorblind }
 }
 
 // --- Constants ---
@@ -63,6 +72,17 @@ pub const CSS_GZ: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/combined.css
 pub const JS_BR:  &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/app.js.br"));
 pub const JS_GZ:  &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/app.js.gz"));
 
+/// A service icon served from `/icons/<key>`; generated by build.rs from `src/icons/`.
+pub struct Icon {
+    pub key: &'static str,
+    pub content_type: &'static str,
+    pub hash: &'static str,
+    pub raw: &'static [u8],
+    pub compressed: Option<(&'static [u8], &'static [u8])>,  // (br, gz) for SVGs
+}
+
+include!(concat!(env!("OUT_DIR"), "/icons.rs"));
+
 pub const FAVICON_ICO: &[u8] = include_bytes!("favicon/favicon.ico");
 pub const FAVICON_SVG: &str = include_str!("favicon/favicon.svg");
 pub const APPLE_TOUCH_ICON: &[u8] = include_bytes!("favicon/apple-touch-icon.png");
@@ -75,6 +95,60 @@ pub const FAVICON_512: &[u8] = include_bytes!("favicon/favicon-512.png");
 pub struct Host {
     pub addr: String,
     pub label: String,
+    /// Non-empty makes this a group card: each member is pinged and stored under its
+    /// own addr, and the card rolls them up. `addr` is then just the card's identifier.
+    #[serde(default)]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub require: Require,
+    /// ICMP source address / interface for this host's pings; overrides the
+    /// top-level `icmp_source` / `icmp_interface`.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Not polled; the card stays, greyed, with its existing history.
+    #[serde(default)]
+    pub paused: bool,
+    /// Higher goes first in each round — started first, so least likely to be cut
+    /// off by `round_deadline_secs`. Equal priorities keep config order.
+    #[serde(default)]
+    pub priority: i32,
+    /// Seconds between checks of this host (every member, for a group card);
+    /// default `poll_interval_secs`.
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Per-check timeout; default `ping_timeout_secs`.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Notification channels (`NOTIFY_CHANNELS`) this host's transitions go to.
+    /// Unset: every configured channel; empty: none (events are still recorded).
+    #[serde(default)]
+    pub channels: Option<Vec<String>>,
+}
+
+/// How a group card's members roll up into its status.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Require {
+    #[default]
+    All,
+    Any,
+}
+
+impl Host {
+    /// Addresses actually pinged: the members of a group card, else the host itself.
+    pub fn targets(&self) -> &[String] {
+        if self.members.is_empty() { std::slice::from_ref(&self.addr) } else { &self.members }
+    }
+
+    /// Effective ICMP (source address, interface): the host's own, else the config-wide one.
+    pub fn icmp_route<'a>(&'a self, cfg: &'a Config) -> (Option<&'a str>, Option<&'a str>) {
+        (
+            self.source.as_deref().or(cfg.icmp_source.as_deref()),
+            self.interface.as_deref().or(cfg.icmp_interface.as_deref()),
+        )
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -83,21 +157,323 @@ pub struct Service {
     #[serde(default)]
     pub icon: String,
     pub check: String,
-    pub target: String,
+    /// `target` in the config: one target, or a list tried in order until one
+    /// answers (failover — UP if any is). Unused (and optional) for `check = "quorum"`.
+    #[serde(rename = "target", default, deserialize_with = "one_or_many")]
+    pub targets: Vec<String>,
     #[serde(default)]
     pub icon_data: Option<String>,
+    /// `check = "quorum"`: service labels or host addrs this synthetic target is
+    /// derived from, and how many of them must be UP (default: all).
+    #[serde(default)]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub quorum: Option<usize>,
+    /// Not polled; shown greyed with its existing history.
+    #[serde(default)]
+    pub paused: bool,
+    /// `check = "dns"`: record type to query — one of `DNS_RECORDS` (default A).
+    #[serde(default)]
+    pub record: Option<String>,
+    /// `check = "dns"`: name to look up (default `DNS_DEFAULT_NAME`, or the
+    /// nameserver's own reverse name for PTR).
+    #[serde(default)]
+    pub query_name: Option<String>,
+    /// `check = "dns"`, A or AAAA: DOWN unless some answer is this IP.
+    #[serde(default)]
+    pub expect_answer: Option<String>,
+    /// UP faster than this is shown as suspicious (not DOWN) — a near-0ms answer
+    /// usually means a captive portal or hijacked DNS, not the real target.
+    #[serde(default)]
+    pub min_latency_ms: Option<f64>,
+    /// Round order, as for hosts.
+    #[serde(default)]
+    pub priority: i32,
+    /// Check interval and timeout, as for hosts (`exec` times out after
+    /// `exec_timeout_secs` unless set).
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Notification routing, as for hosts.
+    #[serde(default)]
+    pub channels: Option<Vec<String>>,
+    /// false: informational only — shown with its own dot and history, but left out
+    /// of the card's up/total badge, uptime and dot.
+    #[serde(default = "default_include_in_count")]
+    pub include_in_count: bool,
+    /// `check = "http"`: UP only if the final status is in this range, e.g. "200-299"
+    /// or "204" (default `DEFAULT_EXPECT_STATUS`).
+    #[serde(default)]
+    pub expect_status: Option<String>,
+    /// `check = "http"`: User-Agent to send (default `pi-glass/<version>`).
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// `check = "http"`: extra request headers, e.g. `Authorization` or `Host`.
+    /// Values are never logged or shown — only the names.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// `check = "http"`: DOWN ("body mismatch") unless the response body contains
+    /// this text. Only the first `max_body_bytes` bytes are searched.
+    #[serde(default)]
+    pub body_contains: Option<String>,
+    /// `check = "http"`: redirects to follow (default `DEFAULT_MAX_REDIRECTS`; 0 = none,
+    /// judge the 3xx itself) and how much body to read for `body_contains`
+    /// (default `DEFAULT_MAX_BODY_BYTES`).
+    #[serde(default)]
+    pub max_redirects: Option<usize>,
+    #[serde(default)]
+    pub max_body_bytes: Option<usize>,
+    /// `check = "tls"`: DOWN once the leaf certificate is this close to expiry
+    /// (default 14 days).
+    #[serde(default)]
+    pub cert_warn_days: Option<i64>,
+    /// `check = "tls"`: accept certificates that don't chain to a public root
+    /// (self-signed, private CA) — expiry is still checked.
+    #[serde(default)]
+    pub allow_self_signed: bool,
+    /// `check = "exec"`: program and arguments, run directly (no shell). Exit 0 is UP.
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// `ping`/`tcp`: address family to resolve and connect over — one of `FAMILIES`.
+    /// Unset: whatever the resolver returns first. `both` is expanded at load into
+    /// sibling services `<label>:v4` and `<label>:v6`.
+    #[serde(default)]
+    pub family: Option<String>,
+    /// Card to show this service in. Once any service has a group, cards are the
+    /// groups (config order, ungrouped ones under `DEFAULT_GROUP`) instead of the
+    /// per-check-type split.
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// Card title for ungrouped services when other services have a `group`.
+pub const DEFAULT_GROUP: &str = "Other";
+
+/// One column of the stats table: a rolling window and its header label.
+#[derive(Deserialize, Clone)]
+pub struct StatsWindow {
+    pub minutes: i64,
+    pub label: String,
+}
+
+/// A known outage, acknowledged in config: while `key` (host addr or `svc:<label>`)
+/// is DOWN in the first outage starting at or after `since` (RFC 3339), its card
+/// shows the note and DOWN notifications for it are suppressed. Once it recovers
+/// the ack is spent. Config rather than an API on purpose — nothing from HTTP is
+/// ever written to disk.
+#[derive(Deserialize, Clone)]
+pub struct Ack {
+    pub key: String,
+    pub since: String,
+    pub note: String,
+}
+
+impl Ack {
+    /// `since` in the stored timestamps' format, so it compares with them as text.
+    fn since_local(&self) -> Option<String> {
+        chrono::DateTime::parse_from_rfc3339(&self.since).ok().map(|t| t.with_timezone(&Local).to_rfc3339())
+    }
+}
+
+/// Nightly window ("HH:MM" local, may wrap midnight) in which notifications are held
+/// back, except for `critical_override` keys. Transitions are still recorded; with
+/// `summary`, the held ones go out as one message when the window ends.
+#[derive(Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub critical_override: Vec<String>,
+    #[serde(default)]
+    pub summary: bool,
+}
+
+impl QuietHours {
+    /// Whether `t` falls in [start, end). Unparseable bounds never match (validate reports them).
+    pub fn contains(&self, t: chrono::NaiveTime) -> bool {
+        match (parse_hh_mm(&self.start), parse_hh_mm(&self.end)) {
+            (Some(start), Some(end)) if start <= end => start <= t && t < end,
+            (Some(start), Some(end)) => t >= start || t < end,
+            _ => false,
+        }
+    }
+}
+
+/// "HH:MM" (or "HH") as a time of day.
+pub fn parse_hh_mm(hh_mm: &str) -> Option<chrono::NaiveTime> {
+    let mut it = hh_mm.trim().splitn(2, ':');
+    let h: u32 = it.next()?.parse().ok()?;
+    let m: u32 = match it.next() {
+        Some(m) => m.parse().ok()?,
+        None => 0,
+    };
+    chrono::NaiveTime::from_hms_opt(h, m, 0)
 }
 
+/// Per-client-IP token bucket for the expensive (DB-scanning) endpoints.
+/// `per_minute` is the sustained refill rate; `burst` the bucket size. 0 disables.
 #[derive(Deserialize)]
+pub struct RateLimit {
+    #[serde(default = "default_rate_per_minute")]
+    pub per_minute: u32,
+    #[serde(default = "default_rate_burst")]
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self { per_minute: default_rate_per_minute(), burst: default_rate_burst() }
+    }
+}
+
+/// HTTP Basic auth for every route, except the static assets (and `/metrics`) when
+/// marked public. `password_hash` is bcrypt, e.g. from `htpasswd -nbB user pass`.
+#[derive(Deserialize)]
+pub struct AuthConfig {
+    pub username: String,
+    pub password_hash: String,
+    /// CSS, JS, fonts, icons and favicons carry no monitoring data.
+    #[serde(default = "default_public_assets")]
+    pub public_assets: bool,
+    #[serde(default)]
+    pub public_metrics: bool,
+}
+
+/// Lowest 1h uptime (%) for each badge colour. Below `degraded` but above 0 is
+/// critical; exactly 0 is down.
+#[derive(Deserialize)]
+pub struct Tiers {
+    #[serde(default = "default_tier_perfect")]
+    pub perfect: f64,
+    #[serde(default = "default_tier_good")]
+    pub good: f64,
+    #[serde(default = "default_tier_degraded")]
+    pub degraded: f64,
+}
+
+impl Default for Tiers {
+    fn default() -> Self {
+        Self { perfect: default_tier_perfect(), good: default_tier_good(), degraded: default_tier_degraded() }
+    }
+}
+
+/// How `pi-glass-mailer` delivers: the Mailgun HTTP API, or any SMTP server.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    #[default]
+    Mailgun,
+    Smtp,
+}
+
+/// SMTP transport security: STARTTLS upgrade (default, port 587), implicit TLS
+/// (port 465), or plaintext (port 25, e.g. a local Postfix).
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    #[default]
+    Starttls,
+    Tls,
+    None,
+}
+
+#[derive(Deserialize, Clone)]
 pub struct MailerConfig {
+    #[serde(default)]
+    pub backend: MailBackend,
+    /// `backend = "mailgun"` (the default).
+    #[serde(default)]
     pub mailgun_domain: String,
+    #[serde(default)]
     pub mailgun_api_key: String,
+    /// `backend = "smtp"`. The port defaults by `smtp_tls`: 587, 465 or 25.
+    #[serde(default)]
+    pub smtp_host: String,
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub smtp_tls: SmtpTls,
+    /// SMTP AUTH credentials; both unset to send unauthenticated.
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
     pub from: String,
     pub to: Vec<String>,
     #[serde(default = "default_mail_subject")]
     pub subject: String,
-    #[serde(default = "default_send_at")]
-    pub send_at: String,
+    /// "HH:MM", or a list of them for several digests a day.
+    #[serde(default = "default_send_at", deserialize_with = "one_or_many")]
+    pub send_at: Vec<String>,
+    /// Lowest-uptime targets listed at the top of the email; 0 hides the block.
+    #[serde(default = "default_top_issues")]
+    pub top_issues: usize,
+    /// Add each host's and service's 24h latency chart — a trend report, at the
+    /// cost of a much larger email.
+    #[serde(default)]
+    pub include_charts: bool,
+    /// pi-glass itself mails each UP↔DOWN change ("DOWN: Gateway") as the `email`
+    /// notification channel, besides the daily digest.
+    #[serde(default)]
+    pub alert_on_change: bool,
+    /// Consecutive DOWN checks before the DOWN mail goes out; shorter outages send
+    /// nothing, not even the recovery.
+    #[serde(default = "default_alert_after_n")]
+    pub alert_after_n: u32,
+    /// Skip the daily digest when nothing was DOWN in the last 24h.
+    #[serde(default)]
+    pub send_only_on_incident: bool,
+    /// Attach the last 24h of checks to the daily digest as a CSV file.
+    #[serde(default)]
+    pub attach_csv: bool,
+}
+
+/// Outage notifications via a Telegram bot (`sendMessage`).
+#[derive(Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+/// Outage notifications via ntfy (ntfy.sh or self-hosted).
+#[derive(Deserialize)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_url")]
+    pub url: String,
+    pub topic: String,
+    /// Access token for protected topics, sent as a bearer token.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Outage notifications via Pushover: application `token` and `user` key.
+#[derive(Deserialize)]
+pub struct PushoverConfig {
+    pub token: String,
+    pub user: String,
+}
+
+/// Each transition POSTed to `url` as JSON, for your own automation.
+#[derive(Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+}
+
+/// Outage notifications via a Discord or Slack incoming webhook (`webhook_url`).
+#[derive(Deserialize)]
+pub struct ChatWebhookConfig {
+    pub webhook_url: String,
+}
+
+/// Graphite plaintext push of each round's results (carbon line protocol over TCP).
+#[derive(Deserialize)]
+pub struct GraphiteConfig {
+    pub host: String,
+    #[serde(default = "default_graphite_port")]
+    pub port: u16,
+    #[serde(default = "default_graphite_prefix")]
+    pub prefix: String,
 }
 
 #[derive(Deserialize)]
@@ -106,6 +482,12 @@ pub struct Config {
     pub name: String,
     #[serde(default = "default_listen")]
     pub listen: String,
+    /// PEM certificate chain and private key. Both set: serve HTTPS on `listen`
+    /// instead of plain HTTP.
+    #[serde(default)]
+    pub tls_cert: Option<String>,
+    #[serde(default)]
+    pub tls_key: Option<String>,
     #[serde(default = "default_db_path")]
     pub db_path: String,
     #[serde(default = "default_poll_interval")]
@@ -116,12 +498,109 @@ pub struct Config {
     pub retention_days: i64,
     #[serde(default = "default_wal_mode")]
     pub wal_mode: bool,
+    /// Store latency as INTEGER microseconds (`latency_us`) instead of REAL
+    /// milliseconds — smaller rows, exact aggregates. Reads accept either column.
+    #[serde(default)]
+    pub latency_us: bool,
     #[serde(default = "default_hosts")]
     pub hosts: Vec<Host>,
     #[serde(default = "default_services")]
     pub services: Vec<Service>,
+    /// Bind ICMP sockets to this source address (e.g. the LTE backup's IP) so pings
+    /// egress a specific path. Unset: the OS picks by routing table.
+    #[serde(default)]
+    pub icmp_source: Option<String>,
+    /// Bind ICMP sockets to this interface (SO_BINDTODEVICE, Linux only).
+    #[serde(default)]
+    pub icmp_interface: Option<String>,
+    /// Checks in flight at once within a round. 1 (default) = strictly sequential.
+    #[serde(default = "default_max_concurrent_checks")]
+    pub max_concurrent_checks: usize,
+    /// Enables `check = "exec"` services. Off by default: they run programs as the
+    /// pi-glass user, with its privileges (including CAP_NET_RAW).
+    #[serde(default)]
+    pub allow_exec: bool,
+    /// Kill an exec check's command (and record DOWN) after this long.
+    #[serde(default = "default_exec_timeout")]
+    pub exec_timeout_secs: u64,
+    /// Checks still pending this long after a round starts are recorded DOWN.
+    #[serde(default = "default_round_deadline")]
+    pub round_deadline_secs: u64,
+    /// A target with no history at startup has its first N failures stored as
+    /// PENDING (not counted against uptime) until it is first seen UP. 0 = off.
+    #[serde(default)]
+    pub grace_polls: u32,
+    /// Flap suppression: notifications and the event log only see a target go DOWN
+    /// after this many consecutive DOWN checks, and UP again after `up_after_checks`
+    /// UP ones. Stored checks and uptime are unaffected.
+    #[serde(default = "default_down_after_checks")]
+    pub down_after_checks: u32,
+    #[serde(default = "default_up_after_checks")]
+    pub up_after_checks: u32,
+    #[serde(default = "default_stats_windows")]
+    pub stats_windows: Vec<StatsWindow>,
+    /// Rows in the expanded "Last N" table of host and service cards.
+    #[serde(default = "default_host_detail_rows")]
+    pub host_detail_rows: usize,
+    #[serde(default = "default_service_detail_rows")]
+    pub service_detail_rows: usize,
+    /// Sparklines cover the last N minutes (at most `SPARK_BARS` checks) instead of
+    /// the last `SPARK_BARS` checks, so every target shows the same wall-clock span.
+    #[serde(default)]
+    pub sparkline_minutes: Option<i64>,
+    /// Service cards show a warning dot when their combined 1h loss exceeds this
+    /// percentage, even while every member is currently UP.
+    #[serde(default)]
+    pub degraded_loss_pct: Option<f64>,
+    #[serde(default)]
+    pub tiers: Tiers,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
     #[serde(default)]
     pub mailer: Option<MailerConfig>,
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
+    #[serde(default)]
+    pub ntfy: Option<NtfyConfig>,
+    #[serde(default)]
+    pub pushover: Option<PushoverConfig>,
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+    #[serde(default)]
+    pub discord: Option<ChatWebhookConfig>,
+    #[serde(default)]
+    pub slack: Option<ChatWebhookConfig>,
+    #[serde(default)]
+    pub graphite: Option<GraphiteConfig>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    /// Replaces the built-in footer. Trusted HTML from the config file — not escaped.
+    #[serde(default)]
+    pub footer_html: Option<String>,
+    #[serde(default = "default_show_footer")]
+    pub show_footer: bool,
+    #[serde(default)]
+    pub acks: Vec<Ack>,
+    /// Enables `GET /ping?target=…&type=…` ad-hoc probes for requests carrying
+    /// `Authorization: Bearer <probe_token>`. Unset: the route answers 404.
+    #[serde(default)]
+    pub probe_token: Option<String>,
+    /// Append one JSON object per check result to this file (JSON lines).
+    #[serde(default)]
+    pub check_log: Option<String>,
+    /// Rotate `check_log` to `<check_log>.1` once it exceeds this size.
+    #[serde(default = "default_check_log_max_bytes")]
+    pub check_log_max_bytes: u64,
+    /// Refuse to start on a config error instead of falling back to the demo defaults.
+    /// Also enabled by `--strict`.
+    #[serde(default)]
+    pub strict: bool,
+    /// IANA zone ("Europe/Dublin") for displayed times, quiet_hours and the mailer's
+    /// send_at. Unset or unknown = the system timezone. Storage is unaffected.
+    #[serde(default)]
+    pub timezone: Option<String>,
 }
 
 fn default_name() -> String { "pi-glass".to_string() }
@@ -131,25 +610,64 @@ fn default_poll_interval() -> u64 { DEFAULT_POLL_INTERVAL_SECS }
 fn default_ping_timeout() -> u64 { DEFAULT_PING_TIMEOUT_SECS }
 fn default_retention_days() -> i64 { DEFAULT_RETENTION_DAYS }
 fn default_wal_mode() -> bool { !cfg!(feature = "openwrt") }
+fn default_max_concurrent_checks() -> usize { 1 }
+fn default_down_after_checks() -> u32 { 3 }
+fn default_up_after_checks() -> u32 { 2 }
+fn default_show_footer() -> bool { true }
+fn default_include_in_count() -> bool { true }
+fn default_round_deadline() -> u64 { 60 }
+fn default_exec_timeout() -> u64 { 10 }
+fn default_host_detail_rows() -> usize { 20 }
+fn default_service_detail_rows() -> usize { 10 }
+fn default_tier_perfect() -> f64 { 100.0 }
+fn default_tier_good() -> f64 { 99.0 }
+fn default_tier_degraded() -> f64 { 95.0 }
+fn default_rate_per_minute() -> u32 { 60 }
+fn default_rate_burst() -> u32 { 20 }
+fn default_public_assets() -> bool { true }
 fn default_mail_subject() -> String { "pi-glass status".to_string() }
-fn default_send_at() -> String { "08:00".to_string() }
+fn default_send_at() -> Vec<String> { vec!["08:00".to_string()] }
+
+/// `"08:00"` or `["08:00", "18:00"]` (also a service's `target`).
+fn one_or_many<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany { One(String), Many(Vec<String>) }
+    Ok(match OneOrMany::deserialize(d)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+fn default_top_issues() -> usize { 5 }
+fn default_alert_after_n() -> u32 { 2 }
+fn default_ntfy_url() -> String { "https://ntfy.sh".to_string() }
+fn default_graphite_port() -> u16 { 2003 }
+fn default_graphite_prefix() -> String { "pi_glass".to_string() }
+fn default_check_log_max_bytes() -> u64 { 10 * 1024 * 1024 }
+
+fn default_stats_windows() -> Vec<StatsWindow> {
+    [(5, "5m"), (60, "1h"), (1440, "24h"), (10080, "7d")]
+        .into_iter()
+        .map(|(minutes, label)| StatsWindow { minutes, label: label.to_string() })
+        .collect()
+}
 
 fn default_hosts() -> Vec<Host> {
     vec![
-        Host { addr: "192.168.1.1".into(), label: "Gateway".into() },
+        Host { addr: "192.168.1.1".into(), label: "Gateway".into(), members: Vec::new(), require: Require::All, source: None, interface: None, paused: false, priority: 0, interval_secs: None, timeout_secs: None, channels: None },
     ]
 }
 
 fn default_services() -> Vec<Service> {
     vec![
-        Service { label: "Google".into(),         icon: "google".into(),     check: "ping".into(), target: "google.com".into(),           icon_data: None },
-        Service { label: "Cloudflare".into(),     icon: "cloudflare".into(), check: "tcp".into(),  target: "cloudflare.com:443".into(),   icon_data: None },
-        Service { label: "YouTube".into(),        icon: "youtube".into(),    check: "tcp".into(),  target: "youtube.com:443".into(),      icon_data: None },
-        Service { label: "Outlook".into(),        icon: "outlook".into(),    check: "tcp".into(),  target: "outlook.com:443".into(),      icon_data: None },
-        Service { label: "WhatsApp".into(),       icon: "whatsapp".into(),   check: "tcp".into(),  target: "web.whatsapp.com:443".into(), icon_data: None },
-        Service { label: "Cloudflare DNS".into(), icon: "cloudflare".into(), check: "dns".into(),  target: "1.1.1.1".into(),             icon_data: None },
-        Service { label: "Google DNS".into(),     icon: "google".into(),     check: "dns".into(),  target: "8.8.8.8".into(),             icon_data: None },
-        Service { label: "Quad9 DNS".into(),      icon: "quad9".into(),      check: "dns".into(),  target: "9.9.9.9".into(),             icon_data: None },
+        Service::new("Google",         "google",     "ping", "google.com"),
+        Service::new("Cloudflare",     "cloudflare", "tcp",  "cloudflare.com:443"),
+        Service::new("YouTube",        "youtube",    "tcp",  "youtube.com:443"),
+        Service::new("Outlook",        "outlook",    "tcp",  "outlook.com:443"),
+        Service::new("WhatsApp",       "whatsapp",   "tcp",  "web.whatsapp.com:443"),
+        Service::new("Cloudflare DNS", "cloudflare", "dns",  "1.1.1.1"),
+        Service::new("Google DNS",     "google",     "dns",  "8.8.8.8"),
+        Service::new("Quad9 DNS",      "quad9",      "dns",  "9.9.9.9"),
     ]
 }
 
@@ -158,21 +676,607 @@ impl Default for Config {
         Self {
             name: default_name(),
             listen: default_listen(),
+            tls_cert: None,
+            tls_key: None,
             db_path: default_db_path(),
             poll_interval_secs: default_poll_interval(),
             ping_timeout_secs: default_ping_timeout(),
             retention_days: default_retention_days(),
             wal_mode: default_wal_mode(),
+            latency_us: false,
             hosts: default_hosts(),
             services: default_services(),
+            icmp_source: None,
+            icmp_interface: None,
+            max_concurrent_checks: default_max_concurrent_checks(),
+            allow_exec: false,
+            exec_timeout_secs: default_exec_timeout(),
+            round_deadline_secs: default_round_deadline(),
+            grace_polls: 0,
+            down_after_checks: default_down_after_checks(),
+            up_after_checks: default_up_after_checks(),
+            stats_windows: default_stats_windows(),
+            host_detail_rows: default_host_detail_rows(),
+            service_detail_rows: default_service_detail_rows(),
+            sparkline_minutes: None,
+            degraded_loss_pct: None,
+            tiers: Tiers::default(),
+            rate_limit: RateLimit::default(),
+            auth: None,
             mailer: None,
+            telegram: None,
+            ntfy: None,
+            pushover: None,
+            webhook: None,
+            discord: None,
+            slack: None,
+            quiet_hours: None,
+            graphite: None,
+            footer_html: None,
+            show_footer: default_show_footer(),
+            acks: Vec::new(),
+            probe_token: None,
+            check_log: None,
+            check_log_max_bytes: default_check_log_max_bytes(),
+            strict: false,
+            timezone: None,
+        }
+    }
+}
+
+/// Check types the poll loop knows how to run. `quorum` is synthetic: computed at the
+/// end of each round from its members' results in that round.
+pub const CHECK_TYPES: &[&str] = &["ping", "tcp", "http", "tls", "dns", "exec", "quorum"];
+
+/// `check = "http"` statuses counted as UP when `expect_status` is unset.
+pub const DEFAULT_EXPECT_STATUS: (u16, u16) = (200, 399);
+
+/// `check = "http"` redirects followed when `max_redirects` is unset.
+pub const DEFAULT_MAX_REDIRECTS: usize = 3;
+
+/// `check = "http"` with `body_contains`: how much of the body is read looking for
+/// it when `max_body_bytes` is unset.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// "200-299" or "204" as an inclusive status range.
+pub fn parse_status_range(s: &str) -> Option<(u16, u16)> {
+    let (lo, hi) = s.split_once('-').unwrap_or((s, s));
+    let (lo, hi): (u16, u16) = (lo.trim().parse().ok()?, hi.trim().parse().ok()?);
+    ((100..=599).contains(&lo) && (lo..=599).contains(&hi)).then_some((lo, hi))
+}
+
+/// Values of a service's `family`.
+pub const FAMILIES: &[&str] = &["v4", "v6", "both"];
+
+/// Names a host or service may list in `channels`, one per notifier table.
+pub const NOTIFY_CHANNELS: &[&str] = &["telegram", "ntfy", "pushover", "email", "webhook", "discord", "slack"];
+
+impl Service {
+    /// A service with every optional setting at its config-file default.
+    pub fn new(label: &str, icon: &str, check: &str, target: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            icon: icon.to_string(),
+            check: check.to_string(),
+            targets: vec![target.to_string()],
+            icon_data: None,
+            members: Vec::new(),
+            quorum: None,
+            paused: false,
+            record: None,
+            query_name: None,
+            expect_answer: None,
+            min_latency_ms: None,
+            priority: 0,
+            interval_secs: None,
+            timeout_secs: None,
+            channels: None,
+            include_in_count: default_include_in_count(),
+            expect_status: None,
+            user_agent: None,
+            headers: BTreeMap::new(),
+            body_contains: None,
+            max_redirects: None,
+            max_body_bytes: None,
+            cert_warn_days: None,
+            allow_self_signed: false,
+            command: Vec::new(),
+            family: None,
+            group: None,
+        }
+    }
+
+    /// Members that must be UP for a quorum service: `quorum`, else all of them.
+    pub fn quorum_needed(&self) -> usize {
+        self.quorum.unwrap_or(self.members.len())
+    }
+
+    /// Whether this service counts towards its card's rollup: polled and not
+    /// `include_in_count = false`.
+    pub fn counted(&self) -> bool {
+        !self.paused && self.include_in_count
+    }
+
+    /// HTTP statuses counted as UP: `expect_status`, else `DEFAULT_EXPECT_STATUS`.
+    pub fn expected_status(&self) -> (u16, u16) {
+        self.expect_status.as_deref().and_then(parse_status_range).unwrap_or(DEFAULT_EXPECT_STATUS)
+    }
+
+    /// UP, but quicker than `min_latency_ms` allows.
+    pub fn too_fast(&self, status: &str, latency: Option<f64>) -> bool {
+        status == "UP" && matches!((self.min_latency_ms, latency), (Some(min), Some(ms)) if ms < min)
+    }
+
+    /// The first (or only) target; empty when none is set.
+    pub fn target(&self) -> &str {
+        self.targets.first().map_or("", String::as_str)
+    }
+
+    /// DNS check question for `nameserver`: (name, QTYPE). PTR asks for the
+    /// nameserver's own reverse name; every other type asks about google.com.
+    pub fn dns_question(&self, nameserver: &str) -> (String, u16) {
+        let record = self.record.as_deref().unwrap_or("A");
+        let qtype = dns_qtype(record).unwrap_or(1);
+        let name = match (&self.query_name, nameserver.parse::<IpAddr>()) {
+            (Some(name), _) => name.clone(),
+            (None, Ok(ip)) if qtype == 12 => reverse_dns_name(ip),
+            _ => DNS_DEFAULT_NAME.to_string(),
+        };
+        (name, qtype)
+    }
+}
+
+/// Fits a DNS question: dot-separated labels of 1–63 letters, digits, `-` or `_`,
+/// 253 bytes in all.
+fn valid_dns_name(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    !name.is_empty() && name.len() <= 253 && name.split('.').all(|l| {
+        (1..=63).contains(&l.len()) && l.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    })
+}
+
+/// Record types a dns check can ask for, with their QTYPE codes.
+pub const DNS_RECORDS: &[(&str, u16)] = &[("A", 1), ("AAAA", 28), ("MX", 15), ("TXT", 16), ("PTR", 12)];
+
+pub const DNS_DEFAULT_NAME: &str = "google.com";
+
+pub fn dns_qtype(record: &str) -> Option<u16> {
+    DNS_RECORDS.iter().find(|(r, _)| r.eq_ignore_ascii_case(record)).map(|&(_, t)| t)
+}
+
+pub fn dns_record_name(qtype: u16) -> &'static str {
+    DNS_RECORDS.iter().find(|&&(_, t)| t == qtype).map_or("?", |&(r, _)| r)
+}
+
+/// `in-addr.arpa` / `ip6.arpa` name for a PTR lookup of `ip`.
+pub fn reverse_dns_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(v6) => {
+            let mut name = String::new();
+            for byte in v6.octets().iter().rev() {
+                write!(name, "{:x}.{:x}.", byte & 0xf, byte >> 4).unwrap();
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
+
+impl Config {
+    /// Human name for a DB key: the service label, the host label, or for a group
+    /// member "Group (addr)".
+    pub fn label_for(&self, key: &str) -> String {
+        if let Some(label) = key.strip_prefix("svc:") {
+            return label.to_string();
+        }
+        for h in &self.hosts {
+            if h.members.iter().any(|m| m == key) {
+                return format!("{} ({key})", h.label);
+            }
+            if h.addr == key {
+                return h.label.clone();
+            }
+        }
+        key.to_string()
+    }
+
+    /// DB key for a quorum member: `svc:<label>` if it names a service, else a host addr.
+    pub fn member_key(&self, member: &str) -> String {
+        if self.services.iter().any(|s| s.label == member) {
+            format!("svc:{member}")
+        } else {
+            member.to_string()
+        }
+    }
+
+    /// What produced `key`'s rows, stored in `ping_results.kind`: the service's check
+    /// type ("tcp", "dns", ...), "host" for a LAN host or group member. `None` for a
+    /// service no longer in the config.
+    pub fn kind_for(&self, key: &str) -> Option<&str> {
+        match key.strip_prefix("svc:") {
+            Some(label) => self.services.iter().find(|s| s.label == label).map(|s| s.check.as_str()),
+            None => Some("host"),
+        }
+    }
+
+    /// Seconds between checks of `key`: its own `interval_secs`, else `poll_interval_secs`.
+    pub fn interval_for(&self, key: &str) -> u64 {
+        let own = match key.strip_prefix("svc:") {
+            Some(label) => self.services.iter().find(|s| s.label == label).and_then(|s| s.interval_secs),
+            None        => self.hosts.iter().find(|h| h.targets().iter().any(|a| a == key)).and_then(|h| h.interval_secs),
+        };
+        own.unwrap_or(self.poll_interval_secs).max(1)
+    }
+
+    /// How often the poll loop wakes: the largest step that lands on every target's
+    /// interval (their gcd), so none is checked late.
+    pub fn tick_secs(&self) -> u64 {
+        fn gcd(a: u64, b: u64) -> u64 { if b == 0 { a } else { gcd(b, a % b) } }
+        self.hosts.iter().map(|h| h.interval_secs)
+            .chain(self.services.iter().map(|s| s.interval_secs))
+            .flatten()
+            .fold(self.poll_interval_secs.max(1), |acc, i| gcd(acc, i.max(1)))
+    }
+
+    /// `strict = true` in the file or `--strict` on the command line.
+    pub fn is_strict(&self) -> bool {
+        self.strict || std::env::args().any(|a| a == "--strict")
+    }
+
+    /// Problems the poll loop would otherwise only discover as a perpetual DOWN —
+    /// unknown check types, `tcp` targets without a port, `dns` targets that aren't
+    /// IPs, ICMP sources that aren't IPs, interfaces that can't be interface names.
+    /// Returns every problem at once, each prefixed with the service or host label.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let sources = std::iter::once(("icmp_source", self.icmp_source.as_ref()))
+            .chain(self.hosts.iter().map(|h| (h.label.as_str(), h.source.as_ref())));
+        for (owner, source) in sources {
+            if let Some(src) = source.filter(|s| s.parse::<IpAddr>().is_err()) {
+                problems.push(format!("{owner}: source '{src}' is not an IP address"));
+            }
+        }
+        let interfaces = std::iter::once(("icmp_interface", self.icmp_interface.as_ref()))
+            .chain(self.hosts.iter().map(|h| (h.label.as_str(), h.interface.as_ref())));
+        for (owner, iface) in interfaces {
+            // Linux interface names: 1..=15 bytes (IFNAMSIZ), no '/' or whitespace.
+            if let Some(i) = iface.filter(|i| i.is_empty() || i.len() > 15 || i.contains(|c: char| c == '/' || c.is_whitespace())) {
+                problems.push(format!("{owner}: interface '{i}' is not a valid interface name"));
+            }
+        }
+        if let Some(m) = &self.mailer {
+            match m.backend {
+                MailBackend::Mailgun if m.mailgun_domain.is_empty() || m.mailgun_api_key.is_empty() => {
+                    problems.push("mailer: mailgun_domain and mailgun_api_key are required (or set backend = \"smtp\")".to_string());
+                }
+                MailBackend::Smtp if m.smtp_host.is_empty() => {
+                    problems.push("mailer: backend = \"smtp\" needs smtp_host".to_string());
+                }
+                MailBackend::Smtp if m.username.is_some() != m.password.is_some() => {
+                    problems.push("mailer: set both username and password, or neither".to_string());
+                }
+                _ => {}
+            }
+            if m.send_at.is_empty() {
+                problems.push("mailer: send_at needs at least one time".to_string());
+            }
+            for at in m.send_at.iter().filter(|t| parse_hh_mm(t).is_none()) {
+                problems.push(format!("mailer: send_at '{at}' is not HH:MM"));
+            }
+        }
+        let mut seen = HashSet::new();
+        for group in self.services.iter().filter_map(|s| s.group.as_deref()).filter(|g| seen.insert(*g)) {
+            if group.contains(['|', '&', ';']) {
+                problems.push(format!("group '{group}': '|', '&' and ';' break remembering whether its card is open"));
+            }
+        }
+        if let Some(tz) = self.timezone.as_deref().filter(|tz| tz.parse::<chrono_tz::Tz>().is_err()) {
+            problems.push(format!("timezone: '{tz}' is not an IANA zone name; using the system timezone"));
+        }
+        if let Some(auth) = &self.auth {
+            if auth.username.contains(':') {
+                problems.push("auth: username may not contain ':'".to_string());
+            }
+            if auth.password_hash.parse::<bcrypt::HashParts>().is_err() {
+                problems.push("auth: password_hash is not a bcrypt hash ($2b$...); every login will fail".to_string());
+            }
+        }
+        if self.down_after_checks == 0 || self.up_after_checks == 0 {
+            problems.push("down_after_checks and up_after_checks must be at least 1".to_string());
+        }
+        let t = &self.tiers;
+        if !(0.0..=100.0).contains(&t.perfect) || t.perfect < t.good || t.good < t.degraded || t.degraded < 0.0 {
+            problems.push(format!(
+                "tiers: need 100 >= perfect >= good >= degraded >= 0 (got {}, {}, {})", t.perfect, t.good, t.degraded,
+            ));
+        }
+        // Not fatal at runtime either: an unresolvable addr is just DOWN every round.
+        for host in &self.hosts {
+            for addr in host.targets().iter().filter(|a| a.parse::<IpAddr>().is_err() && !valid_dns_name(a)) {
+                problems.push(format!("host '{}': addr '{addr}' is not an IP address or hostname", host.label));
+            }
+        }
+        for ack in &self.acks {
+            if !self.has_target(&ack.key) {
+                problems.push(format!("ack '{}': not a host addr or svc:<label>", ack.key));
+            }
+            if ack.since_local().is_none() {
+                problems.push(format!("ack '{}': since '{}' is not an RFC 3339 time like 2026-10-16T09:00:00+02:00", ack.key, ack.since));
+            }
+        }
+        if let Some(q) = &self.quiet_hours {
+            for bound in [&q.start, &q.end].into_iter().filter(|b| parse_hh_mm(b).is_none()) {
+                problems.push(format!("quiet_hours: '{bound}' is not HH:MM"));
+            }
+            for key in q.critical_override.iter().filter(|k| !self.has_target(k)) {
+                problems.push(format!("quiet_hours: critical_override '{key}' is not a host addr or svc:<label>"));
+            }
+        }
+        let routes = self.hosts.iter().map(|h| (format!("host '{}'", h.label), &h.channels))
+            .chain(self.services.iter().map(|s| (format!("service '{}'", s.label), &s.channels)));
+        for (owner, channels) in routes {
+            for ch in channels.iter().flatten().filter(|c| !self.has_channel(c)) {
+                if ch == "email" {
+                    problems.push(format!("{owner}: channel 'email' needs [mailer] alert_on_change = true"));
+                } else if NOTIFY_CHANNELS.contains(&ch.as_str()) {
+                    problems.push(format!("{owner}: channel '{ch}' has no [{ch}] section"));
+                } else {
+                    problems.push(format!("{owner}: unknown channel '{ch}' (expected one of: {})", NOTIFY_CHANNELS.join(", ")));
+                }
+            }
+        }
+        let timings = self.hosts.iter().map(|h| (format!("host '{}'", h.label), h.interval_secs, h.timeout_secs))
+            .chain(self.services.iter().map(|s| (format!("service '{}'", s.label), s.interval_secs, s.timeout_secs)));
+        for (owner, interval, timeout) in timings {
+            if interval == Some(0) {
+                problems.push(format!("{owner}: interval_secs must be at least 1"));
+            }
+            if timeout == Some(0) {
+                problems.push(format!("{owner}: timeout_secs must be at least 1"));
+            }
+        }
+        for svc in &self.services {
+            let label = &svc.label;
+            if let Some(family) = &svc.family {
+                if !FAMILIES.contains(&family.as_str()) {
+                    problems.push(format!("service '{label}': unknown family '{family}' (expected one of: {})", FAMILIES.join(", ")));
+                } else if !matches!(svc.check.as_str(), "ping" | "tcp") {
+                    problems.push(format!("service '{label}': family applies to ping and tcp checks only"));
+                }
+            }
+            if svc.targets.is_empty() && matches!(svc.check.as_str(), "ping" | "tcp" | "dns" | "http" | "tls") {
+                problems.push(format!("service '{label}': {} check needs a target", svc.check));
+            }
+            match svc.check.as_str() {
+                "tcp" | "tls" => {
+                    for target in &svc.targets {
+                        let ok = target.rsplit_once(':').is_some_and(|(host, port)| {
+                            !host.trim_matches(['[', ']']).is_empty() && port.parse::<u16>().is_ok_and(|p| p > 0)
+                        });
+                        if !ok {
+                            problems.push(format!("service '{label}': {} target '{target}' is not host:port", svc.check));
+                        }
+                    }
+                }
+                "http" => {
+                    for target in svc.targets.iter().filter(|t| !(t.starts_with("http://") || t.starts_with("https://"))) {
+                        problems.push(format!("service '{label}': http target '{target}' is not an http(s):// URL"));
+                    }
+                    if let Some(expect) = svc.expect_status.as_deref().filter(|e| parse_status_range(e).is_none()) {
+                        problems.push(format!("service '{label}': expect_status '{expect}' is not a status or range like 200-299"));
+                    }
+                    if svc.body_contains.as_deref() == Some("") {
+                        problems.push(format!("service '{label}': body_contains is empty"));
+                    }
+                    if svc.max_body_bytes == Some(0) {
+                        problems.push(format!("service '{label}': max_body_bytes must be at least 1"));
+                    }
+                    if svc.user_agent.as_deref().is_some_and(|ua| reqwest::header::HeaderValue::from_str(ua).is_err()) {
+                        problems.push(format!("service '{label}': user_agent is not a valid header value"));
+                    }
+                    for (name, value) in &svc.headers {
+                        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                            problems.push(format!("service '{label}': '{name}' is not a valid header name"));
+                        } else if reqwest::header::HeaderValue::from_str(value).is_err() {
+                            // Name only: header values are where the secrets live.
+                            problems.push(format!("service '{label}': header '{name}' has an invalid value"));
+                        }
+                    }
+                }
+                "dns" => {
+                    for target in svc.targets.iter().filter(|t| t.parse::<IpAddr>().is_err()) {
+                        problems.push(format!("service '{label}': dns target '{target}' is not an IP address"));
+                    }
+                    if let Some(record) = svc.record.as_deref().filter(|r| dns_qtype(r).is_none()) {
+                        let known: Vec<&str> = DNS_RECORDS.iter().map(|(r, _)| *r).collect();
+                        problems.push(format!("service '{label}': unknown dns record '{record}' (expected {})", known.join(", ")));
+                    }
+                    if let Some(name) = svc.query_name.as_deref().filter(|n| !valid_dns_name(n)) {
+                        problems.push(format!("service '{label}': query_name '{name}' is not a valid DNS name"));
+                    }
+                    if let Some(expect) = &svc.expect_answer {
+                        match (expect.parse::<IpAddr>(), svc.dns_question(svc.target()).1) {
+                            (Err(_), _) => problems.push(format!("service '{label}': expect_answer '{expect}' is not an IP address")),
+                            (Ok(IpAddr::V4(_)), 1) | (Ok(IpAddr::V6(_)), 28) => {}
+                            (Ok(_), qtype) => problems.push(format!(
+                                "service '{label}': expect_answer '{expect}' can't match {} records", dns_record_name(qtype),
+                            )),
+                        }
+                    }
+                }
+                "ping" => {
+                    if svc.targets.iter().any(String::is_empty) {
+                        problems.push(format!("service '{label}': ping target is empty"));
+                    }
+                }
+                "exec" => {
+                    if svc.command.is_empty() {
+                        problems.push(format!("service '{label}': exec command is empty"));
+                    }
+                    if !self.allow_exec {
+                        problems.push(format!("service '{label}': exec checks are disabled (set allow_exec = true)"));
+                    }
+                }
+                "quorum" => {
+                    if svc.members.is_empty() {
+                        problems.push(format!("service '{label}': quorum has no members"));
+                    }
+                    for m in &svc.members {
+                        let key = self.member_key(m);
+                        if !self.has_target(&key) || self.services.iter().any(|s| s.label == *m && s.check == "quorum") {
+                            problems.push(format!("service '{label}': quorum member '{m}' is not a host addr or non-quorum service label"));
+                        }
+                    }
+                    if svc.quorum.is_some_and(|q| q == 0 || q > svc.members.len()) {
+                        problems.push(format!("service '{label}': quorum must be between 1 and {}", svc.members.len()));
+                    }
+                }
+                other => problems.push(format!(
+                    "service '{label}': unknown check type '{other}' (expected one of: {})",
+                    CHECK_TYPES.join(", "),
+                )),
+            }
+        }
+        problems
+    }
+
+    /// Whether `channel` is configured, i.e. its `[telegram]`/`[ntfy]`/`[pushover]` table is present.
+    fn tz(&self) -> Option<chrono_tz::Tz> {
+        self.timezone.as_deref()?.parse().ok()
+    }
+
+    /// Wall-clock time now in the configured `timezone`.
+    pub fn local_now(&self) -> chrono::NaiveDateTime {
+        let now = chrono::Utc::now();
+        match self.tz() {
+            Some(tz) => now.with_timezone(&tz).naive_local(),
+            None => now.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// Formats `t` (unix seconds) in the configured `timezone`.
+    pub fn fmt_time(&self, t: i64, fmt: &str) -> String {
+        chrono::DateTime::from_timestamp(t, 0).map_or_else(String::new, |t| self.fmt_utc(t, fmt))
+    }
+
+    /// Formats a stored RFC 3339 timestamp in the configured `timezone`.
+    pub fn fmt_stored(&self, ts: &str, fmt: &str) -> String {
+        chrono::DateTime::parse_from_rfc3339(ts).map_or_else(|_| ts.to_string(), |t| self.fmt_utc(t.to_utc(), fmt))
+    }
+
+    fn fmt_utc(&self, t: chrono::DateTime<chrono::Utc>, fmt: &str) -> String {
+        match self.tz() {
+            Some(tz) => t.with_timezone(&tz).format(fmt).to_string(),
+            None => t.with_timezone(&Local).format(fmt).to_string(),
+        }
+    }
+
+    pub fn has_channel(&self, channel: &str) -> bool {
+        match channel {
+            "telegram" => self.telegram.is_some(),
+            "ntfy"     => self.ntfy.is_some(),
+            "pushover" => self.pushover.is_some(),
+            "email"    => self.mailer.as_ref().is_some_and(|m| m.alert_on_change),
+            "webhook"  => self.webhook.is_some(),
+            "discord"  => self.discord.is_some(),
+            "slack"    => self.slack.is_some(),
+            _          => false,
+        }
+    }
+
+    /// Whether transitions of `key` should be sent to `channel`: per the owning
+    /// service's or host's `channels`, else to every channel.
+    pub fn routes_to(&self, key: &str, channel: &str) -> bool {
+        let channels = match key.strip_prefix("svc:") {
+            Some(label) => self.services.iter().find(|s| s.label == label).and_then(|s| s.channels.as_ref()),
+            None        => self.hosts.iter().find(|h| h.targets().iter().any(|a| a == key)).and_then(|h| h.channels.as_ref()),
+        };
+        channels.is_none_or(|c| c.iter().any(|c| c == channel))
+    }
+
+    /// Whether notifications for `key` are held back at `now` by `quiet_hours`.
+    pub fn is_quiet(&self, key: &str, now: chrono::NaiveTime) -> bool {
+        self.quiet_hours.as_ref()
+            .is_some_and(|q| q.contains(now) && !q.critical_override.iter().any(|k| k == key))
+    }
+
+    /// Splits each `family = "both"` ping/tcp service into `<label>:v4` and
+    /// `<label>:v6` siblings, each with its own key and history.
+    pub fn expand_families(&mut self) {
+        self.services = std::mem::take(&mut self.services).into_iter()
+            .flat_map(|svc| {
+                if svc.family.as_deref() == Some("both") && matches!(svc.check.as_str(), "ping" | "tcp") {
+                    ["v4", "v6"].map(|f| Service {
+                        label: format!("{}:{f}", svc.label),
+                        family: Some(f.to_string()),
+                        ..svc.clone()
+                    }).to_vec()
+                } else {
+                    vec![svc]
+                }
+            })
+            .collect();
+    }
+
+    /// Whether the poll loop runs `svc`'s check: a known type with a target, and for
+    /// `exec` only with `allow_exec`. Anything else renders as misconfigured.
+    pub fn is_runnable(&self, svc: &Service) -> bool {
+        CHECK_TYPES.contains(&svc.check.as_str())
+            && (svc.check != "exec" || self.allow_exec)
+            && (matches!(svc.check.as_str(), "exec" | "quorum") || !svc.targets.is_empty())
+    }
+
+    /// True if `key` is a DB key this config writes — a host addr or `svc:<label>`.
+    pub fn has_target(&self, key: &str) -> bool {
+        match key.strip_prefix("svc:") {
+            Some(label) => self.services.iter().any(|s| s.label == label),
+            None        => self.hosts.iter().any(|h| h.targets().iter().any(|a| a == key)),
         }
     }
 }
 
 // --- Config loading ---
 
+/// Value following `flag` on the command line, e.g. `--config <path>`.
+pub fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(a) = args.next() {
+        if a == flag {
+            return args.next();
+        }
+    }
+    None
+}
+
+/// Logs to stderr at the level `RUST_LOG` asks for (`info` by default). Under
+/// systemd, journald adds its own timestamps and colours are off.
+pub fn init_logging() {
+    use std::io::IsTerminal;
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let logger = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal());
+    if std::env::var_os("JOURNAL_STREAM").is_some() {
+        logger.without_time().init();
+    } else {
+        logger.init();
+    }
+}
+
+/// Directory holding config.toml and the default database. `--data-dir <dir>` wins,
+/// then `PI_GLASS_DATA_DIR`, then the platform default.
 pub fn data_dir() -> String {
+    if let Some(dir) = arg_value("--data-dir")
+        .or_else(|| std::env::var("PI_GLASS_DATA_DIR").ok())
+        .filter(|d| !d.is_empty())
+    {
+        return dir;
+    }
     #[cfg(target_os = "windows")]
     {
         std::env::var("LOCALAPPDATA")
@@ -205,23 +1309,23 @@ pub fn bootstrap_config_from_exe() {
 
     let contents = match std::fs::read_to_string(&src) {
         Ok(s) => s,
-        Err(e) => { eprintln!("Warning: could not read {}: {e}", src.display()); return; }
+        Err(e) => { tracing::warn!("could not read {}: {e}", src.display()); return; }
     };
     if let Err(e) = toml::from_str::<toml::Value>(&contents) {
-        eprintln!("Warning: config.toml beside exe is not valid TOML, skipping bootstrap: {e}");
+        tracing::warn!("config.toml beside exe is not valid TOML, skipping bootstrap: {e}");
         return;
     }
 
     if let Some(parent) = dest.parent() {
         if let Err(e) = std::fs::create_dir_all(parent) {
-            eprintln!("Warning: could not create {}: {e}", parent.display());
+            tracing::warn!("could not create {}: {e}", parent.display());
             return;
         }
     }
 
     match std::fs::copy(&src, &dest) {
         Ok(_) => {
-            eprintln!("Bootstrapped config: {} -> {}", src.display(), dest.display());
+            tracing::info!("Bootstrapped config: {} -> {}", src.display(), dest.display());
             use std::io::Write;
             let note = format!("\n# see {}\n", dest.display());
             let _ = std::fs::OpenOptions::new()
@@ -229,7 +1333,7 @@ pub fn bootstrap_config_from_exe() {
                 .open(&src)
                 .and_then(|mut f| f.write_all(note.as_bytes()));
         }
-        Err(e) => eprintln!("Warning: could not bootstrap config: {e}"),
+        Err(e) => tracing::warn!("could not bootstrap config: {e}"),
     }
 }
 
@@ -240,6 +1344,7 @@ pub fn html_escape(s: &str) -> String {
             '&' => out.push_str("&amp;"),
             '<' => out.push_str("&lt;"),
             '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
             c   => out.push(c),
         }
     }
@@ -253,13 +1358,23 @@ pub fn default_config_toml() -> String {
 #   Linux:   /opt/pi-glass/config.toml   (or pass --config <path>)
 #   Windows: %LOCALAPPDATA%\pi-glass\config.toml
 #            (drop beside pi-glass.exe for automatic first-run copy)
+# The data directory (config + database) can be moved with --data-dir <dir>
+# or PI_GLASS_DATA_DIR, e.g. ~/.local/share/pi-glass for an unprivileged user.
 
 # Dashboard name shown in the browser tab and page heading
 name = "pi-glass"
 
+# Timezone for displayed times, quiet_hours and the mailer's send_at (IANA name).
+# Default: the system timezone.
+# timezone = "Europe/Dublin"
+
 # Address and port to listen on
 listen = "0.0.0.0:8080"
 
+# Serve HTTPS instead of HTTP: PEM certificate chain and private key (self-signed is fine)
+# tls_cert = "/etc/pi-glass/cert.pem"
+# tls_key  = "/etc/pi-glass/key.pem"
+
 # SQLite database path (directory is created automatically on first run)
 # db_path = "/opt/pi-glass/pi-glass.db"              # Linux default
 # db_path = "%LOCALAPPDATA%\\pi-glass\\pi-glass.db"  # Windows default
@@ -273,27 +1388,246 @@ ping_timeout_secs = 2
 # Days of history to retain in the database
 retention_days = 7
 
+# Footer on the dashboard and in the daily email: replace it with your own HTML
+# (used as-is, not escaped) or hide it
+# footer_html = 'Monitored by <a href="https://example.com">Example IT</a>'
+# show_footer = false
+
+# Checks in flight at once (1 = one at a time, kindest to embedded routers), and
+# how long a round may run before unfinished checks are recorded DOWN
+# max_concurrent_checks = 1
+# round_deadline_secs   = 60
+
+# Grace for targets with no history yet: their first N failures are stored as
+# "pending" (not counted against uptime, no notification) until first seen UP
+# grace_polls = 3
+
+# Flap suppression: a target only counts as DOWN for notifications and the event
+# log after this many consecutive DOWN checks, and as UP again after this many UP
+# down_after_checks = 3
+# up_after_checks   = 2
+
+# Send pings from a specific source address and/or interface — e.g. to test the
+# LTE backup path on a multi-homed Pi. Per-host `source`/`interface` override these.
+# icmp_source    = "192.168.8.100"
+# icmp_interface = "wwan0"
+
+# Exit on config errors (parse failures, unknown check types, malformed targets)
+# instead of falling back to defaults or marking services misconfigured (= --strict)
+# strict = true
+
+# Service cards turn amber when their combined 1h loss exceeds this percentage,
+# even if every member is UP right now (catches one flapping member in a big card)
+# degraded_loss_pct = 2.0
+
+# Columns of the stats table — rolling windows in minutes with their header labels
+# stats_windows = [
+#   { minutes = 5,     label = "5m"  },
+#   { minutes = 60,    label = "1h"  },
+#   { minutes = 1440,  label = "24h" },
+#   { minutes = 10080, label = "7d"  },
+# ]
+
+# Rows in the "Last N" table when a card is expanded
+# host_detail_rows    = 20
+# service_detail_rows = 10
+
+# Sparklines show the last N minutes rather than the last 40 checks, so targets
+# with slower polls or paused stretches line up in time (still at most 40 bars)
+# sparkline_minutes = 20
+
 # Enable WAL journal mode for concurrent read/write access.
 # Default: true on standard Linux builds, false on OpenWrt builds.
-# Disable if your filesystem doesn't support shared memory (some Pi/NAS mounts).
+# Uses synchronous=NORMAL. If the filesystem doesn't support shared memory (some
+# Pi/NAS mounts) startup warns and falls back to the default journal mode.
 # wal_mode = true
 
+# Store latency as integer microseconds instead of REAL milliseconds — smaller
+# database, exact aggregates, same display. Existing rows stay readable.
+# latency_us = true
+
+# 1h uptime (%) at which badges turn from green (perfect/good) to amber and red
+# [tiers]
+# perfect  = 100.0
+# good     = 99.0     # e.g. 99.9 for internal links: amber sooner
+# degraded = 95.0     # below this (and above 0) is critical
+
+# Per-client rate limit on the API endpoints (the dashboard itself is never limited).
+# Requests beyond the burst get 429 until the bucket refills. per_minute = 0 disables.
+# [rate_limit]
+# per_minute = 60
+# burst      = 20
+
+# Require a password (HTTP Basic auth), e.g. behind a shared reverse proxy. The hash
+# is bcrypt: htpasswd -nbB admin 'secret' | cut -d: -f2
+# [auth]
+# username       = "admin"
+# password_hash  = "$2y$05$..."
+# public_assets  = true    # CSS/JS/fonts/icons without a password
+# public_metrics = false   # /metrics without a password (for a scraper)
+
+# Outage notifications on every UP/DOWN change, e.g. "🔴 NAS is DOWN (was UP 6m)"
+# Any combination of channels may be enabled.
+# [telegram]
+# bot_token = "123456:ABC-..."
+# chat_id   = "123456789"
+#
+# [ntfy]
+# url   = "https://ntfy.sh"   # or your own server
+# topic = "pi-glass-alerts"
+# token = "tk_..."            # optional, for protected topics
+#
+# [pushover]
+# token = "app token"
+# user  = "user key"
+#
+# [webhook]                   # JSON POST per transition, for your own automation
+# url = "https://automation.example.com/pi-glass"
+#
+# [discord]                   # or [slack]; incoming-webhook URL, messages include 1h uptime
+# webhook_url = "https://discord.com/api/webhooks/..."
+
+# Quiet hours: hold back notifications overnight (local time, may wrap midnight).
+# Transitions are still recorded; critical_override targets alert anyway, and
+# summary = true sends what was held back as one message when the window ends.
+# [quiet_hours]
+# start             = "23:00"
+# end               = "07:00"
+# critical_override = ["192.168.1.1", "svc:Internet"]
+# summary           = true
+
+# Ad-hoc diagnostics: GET /ping?target=host:443&type=tcp (type = tcp, ping or dns)
+# runs a one-off check from this box and returns JSON; nothing is stored. Requires
+# "Authorization: Bearer <probe_token>"; the route doesn't exist while this is unset.
+# probe_token = "change-me"
+
+# Acknowledge a known outage: while the target is DOWN its card shows an "ack"
+# badge with this note and DOWN notifications for it are suppressed. An ack covers
+# the first outage starting at or after `since`; once the target recovers, the
+# next outage alerts as usual.
+# [[acks]]
+# key   = "svc:Cloudflare"                # host addr or svc:<label>
+# since = "2026-10-16T09:00:00+02:00"     # the outage's start, or just before it
+# note  = "ISP ticket #123"
+
+# Append-only audit trail of every raw check as JSON lines:
+#   {"ts":"…","key":"svc:Google","status":"DOWN","latency_ms":null,"error":"timeout"}
+# Rotated to <file>.1 at check_log_max_bytes (default 10 MiB), so at most 2× that on disk.
+# check_log = "/opt/pi-glass/checks.jsonl"
+# check_log_max_bytes = 10485760
+
+# Push every round's results to Graphite/carbon (plaintext protocol over TCP) as
+# <prefix>.<target>.up and <prefix>.<target>.latency_ms
+# [graphite]
+# host   = "graphite.lan"
+# port   = 2003
+# prefix = "pi_glass"
+
 # ── LAN Hosts ────────────────────────────────────────────────────
 # Monitored by ICMP ping. Each host gets a collapsible stats card.
 # Requires CAP_NET_RAW on Linux (see deploy/pi-glass.service).
+# paused = true stops polling but keeps the (greyed) card and its history.
+# priority = 10 checks this host ahead of lower-priority targets each round.
+# interval_secs = 5 / timeout_secs = 1 check this host on its own schedule
+# (default: poll_interval_secs / ping_timeout_secs).
+# channels = ["ntfy"] sends this host's alerts to ntfy only; [] to none at all
+# (default: every configured channel).
 
 [[hosts]]
 addr  = "192.168.1.1"
 label = "Gateway"
 
+# addr may also be a hostname (e.g. "router.lan"): it is resolved before the
+# ping, re-resolved every 5 minutes, and a failed lookup is just a DOWN.
+
+# Group card: one card rolled up from several addresses, each pinged and stored
+# separately. require = "all" (default) or "any" member UP for the card to be UP.
+# [[hosts]]
+# addr    = "internet"
+# label   = "Internet"
+# members = ["192.168.1.1", "10.64.0.1", "1.1.1.1"]
+# require = "all"
+
+# Per-host ICMP path: ping this host via the backup link only.
+# [[hosts]]
+# addr      = "1.1.1.1"
+# label     = "Internet via LTE"
+# source    = "192.168.8.100"
+# interface = "wwan0"
+
 # ── External Services ─────────────────────────────────────────────
-# check    : "ping"  — ICMP echo to hostname or IP
-#          : "tcp"   — TCP connect to "host:port"
-#          : "dns"   — UDP DNS A-query to a nameserver IP
+# check    : "ping"   — ICMP echo to hostname or IP
+#          : "tcp"    — TCP connect to "host:port"
+#          : "tls"    — TLS handshake with "host:port" (SNI = host); DOWN if the
+#                       certificate is untrusted or near expiry (cert_warn_days)
+#          : "http"   — GET a URL; UP if the status (after up to max_redirects) is
+#                       in expect_status (default "200-399"); latency = time
+#                       to first byte
+#          : "dns"    — UDP DNS query to a nameserver IP; UP only on a
+#                       NOERROR answer holding the requested record type
+#          : "exec"   — run `command` (argv array, no shell); exit 0 = UP,
+#                       runtime = latency, last stderr line = failure reason.
+#                       Needs allow_exec = true (see below)
+#          : "quorum" — derived from other targets (see below)
 # icon     : built-in key — google, bing, cloudflare, dns,
 #                           youtube, outlook, whatsapp
 # icon_data: base64 data URI override, e.g. "data:image/png;base64,…"
-# target   : hostname (ping), "host:port" (tcp), IP address (dns)
+# target   : hostname (ping), "host:port" (tcp), URL (http), IP address (dns);
+#            or a list, tried in order — UP on the first that answers, e.g.
+#            ["1.1.1.1:53", "8.8.8.8:53", "9.9.9.9:53"] for "is the internet up"
+# expect_status: http only — UP status range, e.g. "200-299" or "204"
+# user_agent / headers: http only — User-Agent (default "pi-glass/<version>")
+#            and extra request headers, e.g.
+#            headers = { Authorization = "Bearer …", Host = "api.internal" };
+#            header values are never logged or shown
+# body_contains: http only — DOWN ("body mismatch") unless this text is in the
+#            first max_body_bytes of the response body, e.g. "Main Page"
+# max_redirects / max_body_bytes: http only — redirects to follow (default 3;
+#            0 judges the redirect itself) and bytes of body read for
+#            body_contains (default 65536)
+# cert_warn_days: tls only — DOWN when the certificate expires within this many
+#            days (default 14); allow_self_signed = true skips chain validation
+# min_latency_ms: flag UP answers faster than this as suspicious (e.g. 5 — a
+#            captive portal or hijacked DNS answers locally in ~0ms)
+# priority : higher is checked first each round (default 0), so it stays fresh
+#            when a slow round runs into round_deadline_secs
+# interval_secs / timeout_secs: this service's own check interval and timeout
+#            (default poll_interval_secs / ping_timeout_secs), e.g. 300 for a
+#            tls expiry check that needn't run every round
+# record   : dns only — "A" (default), "AAAA", "MX", "TXT" or "PTR"
+#            (PTR looks up the nameserver's own reverse name)
+# query_name: dns only — name to resolve (default "google.com"), e.g. an
+#            internal hostname to prove a local resolver really answers
+# channels : notification channels for this service, e.g. ["telegram"];
+#            [] for none (default: all configured)
+# expect_answer: dns A/AAAA only — DOWN ("wrong answer") unless one of the
+#            answers is this IP, e.g. to catch split-horizon leaks
+# paused   : true — stop polling, keep the card (greyed) and its history
+# include_in_count: false — informational: still polled, stored and shown with
+#            its own dot, but left out of the card's up/total count and uptime
+# family   : ping/tcp only — "v4" or "v6" resolves and checks over that family
+#            only; "both" checks each, as sibling rows "<label>:v4" / "<label>:v6"
+#            (refer to those names in quorum members, acks, etc.)
+# group    : card to show it in, e.g. "Streaming". Once any service has a group the
+#            cards are the groups, in config order (ungrouped ones under "Other"),
+#            instead of Web / ICMP / DNS by check type
+#
+# Synthetic "quorum" service: UP when at least `quorum` of its members (service
+# labels or host addrs) were UP this round; gets its own history like any target.
+# [[services]]
+# label   = "Internet"
+# icon    = "dns"
+# check   = "quorum"
+# members = ["Google", "Cloudflare DNS", "Quad9 DNS"]
+# quorum  = 2
+#
+# Custom "exec" check: any program whose exit code says UP/DOWN. Disabled unless
+# allow_exec = true at the top level; commands run as the pi-glass user with its
+# privileges (including CAP_NET_RAW), killed after exec_timeout_secs (default 10).
+# [[services]]
+# label   = "Printer toner"
+# check   = "exec"
+# command = ["/usr/local/bin/check-toner", "printer.lan"]
 
 [[services]]
 label  = "Google"
@@ -345,56 +1679,91 @@ target = "9.9.9.9"
 "#.to_string()
 }
 
-pub fn load_config() -> (Config, Option<String>) {
-    let path = std::env::args()
-        .nth(1)
-        .filter(|a| a == "--config")
-        .and_then(|_| std::env::args().nth(2))
+/// Loads config.toml. A missing file always falls back to the demo defaults (first
+/// run); an unparseable one does too unless strict mode is on — `--strict`, or
+/// `strict = true` in the file itself — in which case the parse error is returned.
+pub fn load_config() -> Result<(Config, Option<String>), String> {
+    let path = arg_value("--config")
         .unwrap_or_else(|| format!("{}/config.toml", data_dir()));
 
     match std::fs::read_to_string(&path) {
-        Ok(contents) => match toml::from_str(&contents) {
-            Ok(cfg) => {
-                eprintln!("Loaded config from {path}");
-                (cfg, None)
+        Ok(contents) => match toml::from_str::<Config>(&contents) {
+            Ok(mut cfg) => {
+                cfg.expand_families();
+                tracing::info!("Loaded config from {path}");
+                Ok((cfg, None))
             }
             Err(e) => {
-                eprintln!("Failed to parse {path}: {e}, using defaults");
-                (Config::default(), Some(default_config_toml()))
+                // The file didn't deserialize as a Config, but may still be valid TOML
+                // carrying `strict = true` — honour it.
+                let strict = std::env::args().any(|a| a == "--strict")
+                    || contents.parse::<toml::Table>().ok()
+                        .and_then(|t| t.get("strict").and_then(|v| v.as_bool()))
+                        .unwrap_or(false);
+                if strict {
+                    return Err(format!("Failed to parse {path}: {e}"));
+                }
+                tracing::warn!("Failed to parse {path}: {e}, using defaults");
+                Ok((Config::default(), Some(default_config_toml())))
             }
         },
         Err(_) => {
-            eprintln!("No config at {path}, using defaults");
-            (Config::default(), Some(default_config_toml()))
+            tracing::warn!("No config at {path}, using defaults");
+            Ok((Config::default(), Some(default_config_toml())))
         }
     }
 }
 
 // --- Stats queries ---
 
+#[derive(Clone)]
 pub struct WindowStats {
     pub uptime_pct: Option<f64>,
     pub avg_ms: Option<f64>,
     pub min_ms: Option<f64>,
     pub max_ms: Option<f64>,
+    /// Nearest-rank percentiles of UP latency, and jitter — the mean absolute
+    /// difference between consecutive UP samples. Filled for the stats table only
+    /// (`query_all_window_stats`), not for uptime-only lookups.
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+    /// Rows in the window, UP or not — shown as a tooltip so thin data after a
+    /// restart is visibly thin.
+    pub sample_count: i64,
 }
 
 /// Query stats for one time window. Uses prepare_cached — zero recompilation cost.
-/// Four separate calls (5m/1h/24h/7d) are deliberately preferred over a single
-/// combined query: each call does a tight index range scan reading only the rows
-/// in that window. A combined query would always scan the full 7d window (the
-/// largest cutoff in the WHERE clause) for every window, doing more I/O and more
+/// One call per configured window (5m/1h/24h/7d by default) is deliberately preferred
+/// over a single combined query: each call does a tight index range scan reading only
+/// the rows in that window. A combined query would always scan the largest window
+/// (the widest cutoff in the WHERE clause) for every window, doing more I/O and more
 /// CASE evaluations per row — strictly worse on embedded hardware.
 pub fn query_window_stats(db: &Connection, host: &str, minutes: i64) -> WindowStats {
     let cutoff = (Local::now() - chrono::Duration::minutes(minutes)).to_rfc3339();
+    query_stats_since(db, host, &cutoff)
+}
+
+/// Expiry (unix seconds) of the leaf certificate a `tls` check last saw.
+pub fn query_cert_expiry(db: &Connection, key: &str) -> Option<i64> {
+    db.prepare_cached(
+        "SELECT cert_expiry FROM ping_results WHERE host = ?1 AND cert_expiry IS NOT NULL ORDER BY id DESC LIMIT 1",
+    )
+    .unwrap()
+    .query_row(params![key], |row| row.get(0))
+    .ok()
+}
+
+/// Stats over every check after `cutoff` (RFC 3339).
+pub fn query_stats_since(db: &Connection, host: &str, cutoff: &str) -> WindowStats {
     let result = db.prepare_cached(
         "SELECT
             COUNT(*),
             SUM(CASE WHEN status = 'UP' THEN 1 ELSE 0 END),
-            AVG(CASE WHEN status = 'UP' THEN latency_ms END),
-            MIN(CASE WHEN status = 'UP' THEN latency_ms END),
-            MAX(CASE WHEN status = 'UP' THEN latency_ms END)
-        FROM ping_results WHERE host = ?1 AND timestamp > ?2",
+            AVG(CASE WHEN status = 'UP' THEN COALESCE(latency_us / 1000.0, latency_ms) END),
+            MIN(CASE WHEN status = 'UP' THEN COALESCE(latency_us / 1000.0, latency_ms) END),
+            MAX(CASE WHEN status = 'UP' THEN COALESCE(latency_us / 1000.0, latency_ms) END)
+        FROM ping_results WHERE host = ?1 AND timestamp > ?2 AND status != 'PENDING'",
     )
     .unwrap()
     .query_row(params![host, cutoff], |row| {
@@ -408,36 +1777,178 @@ pub fn query_window_stats(db: &Connection, host: &str, minutes: i64) -> WindowSt
             avg_ms: row.get(2)?,
             min_ms: row.get(3)?,
             max_ms: row.get(4)?,
+            p95_ms: None,
+            p99_ms: None,
+            jitter_ms: None,
+            sample_count: total,
         })
     });
-    result.unwrap_or(WindowStats { uptime_pct: None, avg_ms: None, min_ms: None, max_ms: None })
+    result.unwrap_or(WindowStats { uptime_pct: None, avg_ms: None, min_ms: None, max_ms: None, p95_ms: None, p99_ms: None, jitter_ms: None, sample_count: 0 })
 }
 
-/// Convenience wrapper: returns all four standard windows in one call.
-pub fn query_all_window_stats(db: &Connection, host: &str) -> (WindowStats, WindowStats, WindowStats, WindowStats) {
-    (
-        query_window_stats(db, host, 5),
-        query_window_stats(db, host, 60),
-        query_window_stats(db, host, 1440),
-        query_window_stats(db, host, 10080),
+/// Convenience wrapper: returns stats for every configured window, in column order.
+pub fn query_all_window_stats(db: &Connection, host: &str, windows: &[StatsWindow]) -> Vec<WindowStats> {
+    windows.iter()
+        .map(|w| {
+            let cutoff = (Local::now() - chrono::Duration::minutes(w.minutes)).to_rfc3339();
+            let mut stats = query_stats_since(db, host, &cutoff);
+            if w.minutes <= LATENCY_SPREAD_MAX_MINUTES {
+                fill_latency_spread(db, host, &cutoff, &mut stats);
+            }
+            stats
+        })
+        .collect()
+}
+
+/// Longest window that gets p95/p99/jitter: the spread scan reads back every UP row,
+/// which on a 30-day window is too many for each page render. Longer ones show "--".
+pub const LATENCY_SPREAD_MAX_MINUTES: i64 = 1440;
+
+/// p95/p99 and jitter of the UP latencies after `cutoff`. SQLite has no percentile
+/// aggregate, so the window's UP latencies are read back in check order — a separate
+/// scan, only worth it where the table shows them. Jitter needs two samples.
+pub fn fill_latency_spread(db: &Connection, host: &str, cutoff: &str, stats: &mut WindowStats) {
+    let mut stmt = db.prepare_cached(
+        "SELECT COALESCE(latency_us / 1000.0, latency_ms) FROM ping_results
+        WHERE host = ?1 AND timestamp > ?2 AND status = 'UP'
+            AND COALESCE(latency_us / 1000.0, latency_ms) IS NOT NULL
+        ORDER BY id",
     )
+    .unwrap();
+    let mut latencies: Vec<f64> = stmt.query_map(params![host, cutoff], |row| row.get(0))
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default();
+    if latencies.len() >= 2 {
+        let deltas: f64 = latencies.windows(2).map(|w| (w[1] - w[0]).abs()).sum();
+        stats.jitter_ms = Some(deltas / (latencies.len() - 1) as f64);
+    }
+    latencies.sort_by(f64::total_cmp);
+    let rank = |p: f64| latencies.get(((p * latencies.len() as f64).ceil() as usize).saturating_sub(1)).copied();
+    stats.p95_ms = rank(0.95);
+    stats.p99_ms = rank(0.99);
+}
+
+/// Stats-table columns: the configured windows plus, on the live page, "Since start"
+/// — so a 100% that only covers the minutes since a reboot reads as what it is.
+fn with_since_start(db: &Connection, cfg: &Config, key: &str, stats: &[WindowStats], started_at: Option<&str>) -> (Vec<StatsWindow>, Vec<WindowStats>) {
+    let mut windows = cfg.stats_windows.clone();
+    let mut stats: Vec<WindowStats> = stats.to_vec();
+    if let Some(started_at) = started_at {
+        windows.push(StatsWindow { minutes: 0, label: "Since start".to_string() });
+        let mut since_start = query_stats_since(db, key, started_at);
+        let recent = chrono::DateTime::parse_from_rfc3339(started_at)
+            .is_ok_and(|t| Local::now().signed_duration_since(t).num_minutes() <= LATENCY_SPREAD_MAX_MINUTES);
+        if recent {
+            fill_latency_spread(db, key, started_at, &mut since_start);
+        }
+        stats.push(since_start);
+    }
+    (windows, stats)
+}
+
+/// 1h uptime drives the header badge and auto-collapse regardless of which columns
+/// are configured — reuse the table's 1h column when present, query it otherwise.
+fn uptime_1h(db: &Connection, host: &str, windows: &[StatsWindow], stats: &[WindowStats]) -> Option<f64> {
+    match windows.iter().position(|w| w.minutes == 60) {
+        Some(i) => stats[i].uptime_pct,
+        None    => query_window_stats(db, host, 60).uptime_pct,
+    }
+}
+
+/// Status `query_latest_status` reports for a target with no checks yet.
+pub const NO_DATA: &str = "--";
+
+/// Stored instead of DOWN for a new target's failures during `grace_polls`; left out
+/// of every uptime figure.
+pub const PENDING: &str = "PENDING";
+
+/// The debounced state of a target whose recent checks are `statuses`, newest first:
+/// DOWN once the latest `down_n` are all DOWN, UP once the latest `up_n` are all UP,
+/// otherwise still `previous`.
+pub fn debounced_state<'a>(statuses: &[&'a str], previous: &'a str, down_n: usize, up_n: usize) -> &'a str {
+    let Some(&latest) = statuses.first() else { return previous };
+    let run = statuses.iter().take_while(|s| **s == latest).count();
+    match latest {
+        "DOWN" if run >= down_n => "DOWN",
+        "UP" if run >= up_n => "UP",
+        _ => previous,
+    }
+}
+
+/// Not yet seen UP: no checks at all, or only grace-period failures.
+pub fn is_pending(status: &str) -> bool {
+    status == NO_DATA || status == PENDING
 }
 
 pub fn query_latest_status(db: &Connection, host: &str) -> (String, Option<f64>) {
     db.prepare_cached(
-        "SELECT status, latency_ms FROM ping_results WHERE host = ?1 ORDER BY id DESC LIMIT 1",
+        "SELECT status, COALESCE(latency_us / 1000.0, latency_ms) FROM ping_results WHERE host = ?1 ORDER BY id DESC LIMIT 1",
     )
     .unwrap()
     .query_row(params![host], |row| {
         Ok((row.get::<_, String>(0)?, row.get::<_, Option<f64>>(1)?))
     })
-    .unwrap_or(("--".to_string(), None))
+    .unwrap_or((NO_DATA.to_string(), None))
+}
+
+/// Start of `key`'s current DOWN run: its first DOWN check after the last check that
+/// wasn't DOWN. None unless the latest check is DOWN.
+pub fn query_down_since(db: &Connection, key: &str) -> Option<String> {
+    db.prepare_cached(
+        "SELECT MIN(timestamp) FROM ping_results WHERE host = ?1 AND status = 'DOWN'
+           AND id > COALESCE((SELECT MAX(id) FROM ping_results WHERE host = ?1 AND status != 'DOWN'), 0)",
+    )
+    .unwrap()
+    .query_row(params![key], |row| row.get(0))
+    .unwrap_or(None)
+}
+
+/// The `[[acks]]` note covering `key`'s outage that started at `down_since`: one
+/// starting at or after the ack's `since`, with no earlier DOWN since then — an
+/// outage that has already come and gone spent the ack.
+pub fn query_ack<'a>(db: &Connection, cfg: &'a Config, key: &str, down_since: &str) -> Option<&'a str> {
+    cfg.acks.iter()
+        .filter(|a| a.key == key)
+        .find(|a| {
+            let Some(since) = a.since_local() else { return false };
+            let earlier_down: bool = db.prepare_cached(
+                "SELECT EXISTS(SELECT 1 FROM ping_results WHERE host = ?1 AND status = 'DOWN' AND timestamp >= ?2 AND timestamp < ?3)",
+            )
+            .unwrap()
+            .query_row(params![key, since, down_since], |row| row.get(0))
+            .unwrap_or(true);
+            since.as_str() <= down_since && !earlier_down
+        })
+        .map(|a| a.note.as_str())
+}
+
+/// Targets whose latest check is DOWN — every host target (group members count
+/// individually) plus every service, paused ones excluded. Drives the tab status icon.
+pub fn count_down(db: &Connection, cfg: &Config) -> usize {
+    let host_keys = cfg.hosts.iter().filter(|h| !h.paused).flat_map(|h| h.targets()).cloned();
+    let svc_keys = cfg.services.iter().filter(|s| !s.paused).map(|s| format!("svc:{}", s.label));
+    host_keys.chain(svc_keys)
+        .filter(|k| query_latest_status(db, k).0 == "DOWN")
+        .count()
+}
+
+/// Tab icon for `/status.svg?d=N`: green tick when nothing is down, red disc with
+/// the down count otherwise. Pure function of the count, so the URL is cacheable.
+pub fn render_status_icon(down: u32) -> String {
+    if down == 0 {
+        return r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32"><circle cx="16" cy="16" r="16" fill="#107c10"/><path d="M9 16.5l4.5 4.5L23 11.5" fill="none" stroke="#fff" stroke-width="3.5" stroke-linecap="round" stroke-linejoin="round"/></svg>"##.to_string();
+    }
+    let text = if down > 99 { "99+".to_string() } else { down.to_string() };
+    let size = match text.len() { 1 => 22, 2 => 17, _ => 12 };
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32"><circle cx="16" cy="16" r="16" fill="#c50f1f"/><text x="16" y="16" dy=".35em" text-anchor="middle" font-family="sans-serif" font-weight="700" font-size="{size}" fill="#fff">{text}</text></svg>"##,
+    )
 }
 
 pub fn query_recent_checks(db: &Connection, host: &str, limit: i64) -> Vec<(String, String, Option<f64>)> {
     let mut stmt = db
         .prepare_cached(
-            "SELECT timestamp, status, latency_ms FROM ping_results WHERE host = ?1 ORDER BY id DESC LIMIT ?2",
+            "SELECT timestamp, status, COALESCE(latency_us / 1000.0, latency_ms) FROM ping_results WHERE host = ?1 ORDER BY id DESC LIMIT ?2",
         )
         .unwrap();
 
@@ -453,13 +1964,50 @@ pub fn query_recent_checks(db: &Connection, host: &str, limit: i64) -> Vec<(Stri
     .collect()
 }
 
+/// Like `query_recent_checks`, limited to checks after `cutoff` (RFC 3339).
+pub fn query_recent_checks_since(db: &Connection, host: &str, cutoff: &str, limit: i64) -> Vec<(String, String, Option<f64>)> {
+    let mut stmt = db
+        .prepare_cached(
+            "SELECT timestamp, status, COALESCE(latency_us / 1000.0, latency_ms) FROM ping_results WHERE host = ?1 AND timestamp > ?2 ORDER BY id DESC LIMIT ?3",
+        )
+        .unwrap();
+
+    stmt.query_map(params![host, cutoff, limit], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<f64>>(2)?,
+        ))
+    })
+    .unwrap()
+    .filter_map(|r| r.ok())
+    .collect()
+}
+
+/// One target's checks after `cutoff` (RFC 3339), oldest first, as CSV:
+/// `timestamp,status,latency_ms` (latency empty unless UP).
+pub fn query_checks_csv(db: &Connection, host: &str, cutoff: &str) -> String {
+    let mut csv = String::from("timestamp,status,latency_ms\n");
+    let mut stmt = db.prepare_cached(
+        "SELECT timestamp, status, COALESCE(latency_us / 1000.0, latency_ms)
+         FROM ping_results WHERE host = ?1 AND timestamp > ?2 ORDER BY id",
+    ).unwrap();
+    let Ok(mut rows) = stmt.query(params![host, cutoff]) else { return csv };
+    while let Ok(Some(row)) = rows.next() {
+        let (Ok(ts), Ok(status)) = (row.get::<_, String>(0), row.get::<_, String>(1)) else { continue };
+        let latency = row.get::<_, Option<f64>>(2).ok().flatten();
+        writeln!(csv, "{ts},{status},{}", latency.map_or(String::new(), |ms| format!("{ms:.3}"))).unwrap();
+    }
+    csv
+}
+
 pub fn query_card_uptime(db: &Connection, keys: &[String], minutes: i64) -> Option<f64> {
     if keys.is_empty() { return None; }
     let cutoff = (Local::now() - chrono::Duration::minutes(minutes)).to_rfc3339();
     let placeholders = std::iter::repeat("?").take(keys.len()).collect::<Vec<_>>().join(",");
     let sql = format!(
         "SELECT COUNT(*), SUM(CASE WHEN status='UP' THEN 1 ELSE 0 END)
-         FROM ping_results WHERE host IN ({placeholders}) AND timestamp > ?"
+         FROM ping_results WHERE host IN ({placeholders}) AND timestamp > ? AND status != 'PENDING'"
     );
     let mut stmt = db.prepare_cached(&sql).unwrap();
     stmt.query_row(
@@ -477,33 +2025,249 @@ pub fn query_card_uptime(db: &Connection, keys: &[String], minutes: i64) -> Opti
     ).unwrap_or(None)
 }
 
-// --- Formatting ---
+/// Whether any target recorded a DOWN check in the last `minutes`.
+pub fn any_down_since(db: &Connection, minutes: i64) -> bool {
+    let cutoff = (Local::now() - chrono::Duration::minutes(minutes)).to_rfc3339();
+    db.query_row(
+        "SELECT EXISTS(SELECT 1 FROM ping_results WHERE status = 'DOWN' AND timestamp > ?1)",
+        [cutoff],
+        |row| row.get(0),
+    ).unwrap_or(true)
+}
+
+/// Every check of the last `minutes`, oldest first, as CSV:
+/// `timestamp,host,status,latency_ms` (latency empty unless UP).
+pub fn query_samples_csv(db: &Connection, minutes: i64) -> String {
+    let cutoff = (Local::now() - chrono::Duration::minutes(minutes)).to_rfc3339();
+    let mut csv = String::from("timestamp,host,status,latency_ms\n");
+    let mut stmt = db.prepare_cached(
+        "SELECT timestamp, host, status, COALESCE(latency_us / 1000.0, latency_ms)
+         FROM ping_results WHERE timestamp > ?1 ORDER BY id",
+    ).unwrap();
+    let Ok(mut rows) = stmt.query([cutoff]) else { return csv };
+    while let Ok(Some(row)) = rows.next() {
+        let (Ok(ts), Ok(host), Ok(status)) = (row.get::<_, String>(0), row.get::<_, String>(1), row.get::<_, String>(2)) else { continue };
+        let latency = row.get::<_, Option<f64>>(3).ok().flatten();
+        writeln!(csv, "{ts},{},{status},{}", csv_field(&host), latency.map_or(String::new(), |ms| format!("{ms:.3}"))).unwrap();
+    }
+    csv
+}
+
+/// Quotes a CSV field if it holds a comma, quote or newline (labels may).
+fn csv_field(s: &str) -> std::borrow::Cow<'_, str> {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\"")).into()
+    } else {
+        s.into()
+    }
+}
+
+/// One time bucket of a target's history: start (unix seconds), mean UP latency,
+/// and fraction of samples that were UP. Both are `None` for buckets with no samples.
+pub struct HistoryBucket {
+    pub start: i64,
+    pub avg_ms: Option<f64>,
+    pub uptime: Option<f64>,
+}
+
+/// Downsample a target's samples in `[from, to)` (unix seconds) into `buckets`
+/// equal-width time buckets. The range filter uses the (host, timestamp) index; the
+/// grouping is done by SQLite on epoch seconds so only N rows ever leave the query.
+/// Empty buckets are returned too, so gaps stay visible to a chart.
+/// Returns the bucket width in seconds alongside the buckets.
+pub fn query_history_buckets(db: &Connection, host: &str, from: i64, to: i64, buckets: usize) -> (i64, Vec<HistoryBucket>) {
+    let buckets = buckets.max(1);
+    let width = ((to - from).max(1) + buckets as i64 - 1) / buckets as i64;
+    let rfc3339 = |t: i64| {
+        Local.timestamp_opt(t, 0).single().map(|d| d.to_rfc3339()).unwrap_or_default()
+    };
+
+    let mut out: Vec<HistoryBucket> = (0..buckets)
+        .map(|i| HistoryBucket { start: from + i as i64 * width, avg_ms: None, uptime: None })
+        .collect();
+
+    let mut stmt = db.prepare_cached(
+        "SELECT (CAST(strftime('%s', timestamp) AS INTEGER) - ?3) / ?4 AS bucket,
+                AVG(CASE WHEN status = 'UP' THEN COALESCE(latency_us / 1000.0, latency_ms) END),
+                COUNT(*),
+                SUM(CASE WHEN status = 'UP' THEN 1 ELSE 0 END)
+         FROM ping_results WHERE host = ?1 AND timestamp >= ?2 AND timestamp < ?5 AND status != 'PENDING'
+         GROUP BY bucket",
+    ).unwrap();
+    let rows = stmt.query_map(params![host, rfc3339(from), from, width, rfc3339(to)], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<f64>>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+    });
+    if let Ok(rows) = rows {
+        for (idx, avg_ms, total, up) in rows.filter_map(|r| r.ok()) {
+            if let Some(b) = usize::try_from(idx).ok().and_then(|i| out.get_mut(i)) {
+                b.avg_ms = avg_ms;
+                b.uptime = (total > 0).then(|| up as f64 / total as f64);
+            }
+        }
+    }
+    (width, out)
+}
+
+/// One recorded UP/DOWN transition. `t` is unix seconds; `lasted_secs` is how long
+/// the target had been in `previous`.
+pub struct Event {
+    pub key: String,
+    pub t: i64,
+    pub status: String,
+    pub previous: String,
+    pub lasted_secs: i64,
+}
+
+/// Transitions at or after `since` (unix seconds), newest first, optionally for one key.
+pub fn query_events(db: &Connection, key: Option<&str>, since: i64, limit: usize) -> Vec<Event> {
+    let since = Local.timestamp_opt(since, 0).single().map(|d| d.to_rfc3339()).unwrap_or_default();
+    let mut stmt = db.prepare_cached(
+        "SELECT host, CAST(strftime('%s', timestamp) AS INTEGER), status, previous, lasted_secs
+         FROM events WHERE timestamp >= ?1 AND (?2 IS NULL OR host = ?2)
+         ORDER BY id DESC LIMIT ?3",
+    ).unwrap();
+    stmt.query_map(params![since, key, limit as i64], |row| {
+        Ok(Event {
+            key: row.get(0)?,
+            t: row.get(1)?,
+            status: row.get(2)?,
+            previous: row.get(3)?,
+            lasted_secs: row.get(4)?,
+        })
+    })
+    .map(|rows| rows.filter_map(|r| r.ok()).collect())
+    .unwrap_or_default()
+}
+
+// --- Formatting ---
+
+pub fn fmt_pct(v: Option<f64>) -> String {
+    v.map_or("--".into(), |v| {
+        if v <= 0.0 || v >= 100.0 { format!("{v:.0}%") } else { format!("{v:.1}%") }
+    })
+}
+
+pub fn fmt_ms(v: Option<f64>) -> String {
+    v.map_or("--".into(), |v| format!("{v:.1}"))
+}
+
+/// 2880 → "2,880".
+pub fn fmt_count(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if n < 0 {
+        out.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// "45s", "6m", "2h 5m", "3d 4h".
+pub fn fmt_duration(d: std::time::Duration) -> String {
+    let s = d.as_secs();
+    match s {
+        0..=59         => format!("{s}s"),
+        60..=3599      => format!("{}m", s / 60),
+        3600..=86399   => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        _              => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+    }
+}
+
+pub fn fmt_latency(v: Option<f64>) -> String {
+    v.map_or_else(String::new, |v| format!("{v:.0}ms"))
+}
 
-pub fn fmt_pct(v: Option<f64>) -> String {
-    v.map_or("--".into(), |v| {
-        if v <= 0.0 || v >= 100.0 { format!("{v:.0}%") } else { format!("{v:.1}%") }
-    })
+/// Bars in a sparkline; shorter histories are padded on the left.
+pub const SPARK_BARS: usize = 40;
+
+/// The checks behind a target's sparkline per `sparkline_minutes`, newest first.
+pub fn sparkline_checks(db: &Connection, cfg: &Config, key: &str) -> Vec<(String, String, Option<f64>)> {
+    match cfg.sparkline_minutes {
+        Some(minutes) => {
+            let cutoff = (Local::now() - chrono::Duration::minutes(minutes)).to_rfc3339();
+            query_recent_checks_since(db, key, &cutoff, SPARK_BARS as i64)
+        }
+        None => query_recent_checks(db, key, SPARK_BARS as i64),
+    }
 }
 
-pub fn fmt_ms(v: Option<f64>) -> String {
-    v.map_or("--".into(), |v| format!("{v:.1}"))
+/// A target's sparkline. `recent` is its newest-first history when the caller
+/// already has it (count mode reuses it; time mode queries afresh).
+fn render_sparkline(db: &Connection, cfg: &Config, key: &str, recent: Option<&[(String, String, Option<f64>)]>) -> String {
+    match (cfg.sparkline_minutes, recent) {
+        (None, Some(rows)) => fmt_sparkline(&rows[..rows.len().min(SPARK_BARS)]),
+        _ => fmt_sparkline(&sparkline_checks(db, cfg, key)),
+    }
 }
 
-pub fn fmt_latency(v: Option<f64>) -> String {
-    v.map_or_else(String::new, |v| format!("{v:.0}ms"))
+/// Latency stats over a sparkline's UP checks, as shown in its tooltip.
+#[derive(Clone, Copy)]
+pub struct SparkStats {
+    pub avg: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
 }
 
-pub fn fmt_sparkline(checks: &[(String, String, Option<f64>)]) -> String {
-    const SPARK_BARS: usize = 40;
+/// A sparkline before formatting: left padding, bar heights oldest first
+/// (1–100, or 50 throughout for near-constant latency; None = DOWN, Some(0) =
+/// pending), and the tooltip stats (None when every check was DOWN).
+pub struct NormalizedSparkline {
+    pub pad: usize,
+    pub bars: Vec<Option<u32>>,
+    pub stats: Option<SparkStats>,
+}
 
+/// Normalizes newest-first `checks` into bars — pure, no DB or markup. Shared by
+/// the HTML sparkline and `/api/sparkline`, so every renderer draws the same shape.
+pub fn normalize_sparkline(checks: &[(String, String, Option<f64>)]) -> NormalizedSparkline {
     // checks arrive DESC (newest first); reverse for left→right chronological display
     let ordered: Vec<_> = checks.iter().rev().collect();
-
     // Transparent gap bars fill the left side so every sparkline is SPARK_BARS wide.
-    let pad_count = SPARK_BARS.saturating_sub(checks.len());
-    let pad_str = if pad_count > 0 {
-        let mut pads = String::with_capacity(pad_count * 3);
-        for i in 0..pad_count { if i > 0 { pads.push(','); } pads.push_str("50"); }
+    let pad = SPARK_BARS.saturating_sub(checks.len());
+
+    let latencies: Vec<f64> = ordered.iter()
+        .filter_map(|(_, s, l)| if s == "UP" { *l } else { None })
+        .collect();
+
+    if latencies.is_empty() {
+        // No UP yet — DOWN and PENDING floor bars, no latency stats
+        let bars = ordered.iter().map(|(_, status, _)| (status == PENDING).then_some(0)).collect();
+        return NormalizedSparkline { pad, bars, stats: None };
+    }
+    let count = latencies.len() as f64;
+    let avg = latencies.iter().sum::<f64>() / count;
+    let stddev = if count > 1.0 {
+        let var = latencies.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / (count - 1.0);
+        var.sqrt()
+    } else { 0.0 };
+    let min = latencies.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = latencies.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    let bars = ordered.iter().map(|(_, status, latency)| match status.as_str() {
+        "UP" if range < 0.5 => Some(50),  // flat mid-line for very consistent latency
+        "UP" => {
+            let v = latency.unwrap_or(min);
+            Some((1.0 + (v - min) / range * 99.0).round() as u32)
+        }
+        PENDING => Some(0),  // floor bar, but not marked as DOWN
+        _ => None,
+    }).collect();
+    NormalizedSparkline { pad, bars, stats: Some(SparkStats { avg, stddev, min, max }) }
+}
+
+pub fn fmt_sparkline(checks: &[(String, String, Option<f64>)]) -> String {
+    let data = normalize_sparkline(checks);
+
+    let pad_str = if data.pad > 0 {
+        let mut pads = String::with_capacity(data.pad * 3);
+        for i in 0..data.pad { if i > 0 { pads.push(','); } pads.push_str("50"); }
         format!(r#"<span class="spark spark-pad">{{{pads}}}</span>"#)
     } else {
         String::new()
@@ -513,70 +2277,129 @@ pub fn fmt_sparkline(checks: &[(String, String, Option<f64>)]) -> String {
         return pad_str;
     }
 
-    let latencies: Vec<f64> = ordered.iter()
-        .filter_map(|(_, s, l)| if s == "UP" { *l } else { None })
-        .collect();
+    let title = match data.stats {
+        None => format!("{} checks · all down", checks.len()),
+        Some(SparkStats { avg, stddev, min, max }) => format!(
+            "{} checks · avg {avg:.0}ms ±{stddev:.0} · min {min:.0}ms · max {max:.0}ms",
+            checks.len()
+        ),
+    };
 
-    let title: String;
-    let mut values = String::with_capacity(ordered.len() * 3);
-    if latencies.is_empty() {
-        // All DOWN — floor bars, no latency stats
-        title = format!("{} checks · all down", checks.len());
-        for (i, _) in ordered.iter().enumerate() {
-            if i > 0 { values.push(','); }
-            values.push('0');
+    // DOWN bars are floor bars in runs of their own, wrapped so colour-blind mode can
+    // mark them; each run is a separate `{…}` group, drawn flush with its neighbours.
+    let mut values = String::with_capacity(data.bars.len() * 3 + 32);
+    for run in data.bars.chunk_by(|a, b| a.is_some() == b.is_some()) {
+        let nums: Vec<String> = run.iter().map(|b| b.unwrap_or(0).to_string()).collect();
+        if run[0].is_some() {
+            write!(values, "{{{}}}", nums.join(",")).unwrap();
+        } else {
+            write!(values, r#"<span class="spark-down">{{{}}}</span>"#, nums.join(",")).unwrap();
         }
-    } else {
-        let count = latencies.len() as f64;
-        let avg = latencies.iter().sum::<f64>() / count;
-        let stddev = if count > 1.0 {
-            let var = latencies.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / (count - 1.0);
-            var.sqrt()
-        } else { 0.0 };
-        let min = latencies.iter().cloned().fold(f64::INFINITY, f64::min);
-        let max = latencies.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-        let range = max - min;
-
-        for (i, (_, status, latency)) in ordered.iter().enumerate() {
-            if i > 0 { values.push(','); }
-            if status == "UP" {
-                let norm: u32 = if range < 0.5 {
-                    50  // flat mid-line for very consistent latency
-                } else {
-                    let v = latency.unwrap_or(min);
-                    (1.0 + (v - min) / range * 99.0).round() as u32
-                };
-                write!(values, "{norm}").unwrap();
-            } else {
-                values.push('0');  // DOWN → floor bar
+    }
+    format!(r#"{pad_str}<span class="spark" title="{title}">{values}</span>"#)
+}
+
+// --- Latency chart ---
+
+/// Server-rendered SVG line chart of a target's latency over the last `minutes`,
+/// built on the same bucketed query as `/api/history`. Buckets with no UP samples
+/// break the line, so DOWN periods show as gaps rather than being bridged.
+/// Colours use `var()` in `style` so `inline_css_vars` resolves them for email.
+pub fn render_latency_chart(db: &Connection, cfg: &Config, key: &str, minutes: i64) -> String {
+    const BUCKETS: usize = 96;
+    const W: f64 = 600.0;
+    const H: f64 = 120.0;
+    const LEFT: f64 = 44.0;   // room for the y labels
+    const TOP: f64 = 8.0;
+    const BOTTOM: f64 = 100.0;
+
+    let to = Local::now().timestamp();
+    let from = to - minutes * 60;
+    let (width, buckets) = query_history_buckets(db, key, from, to, BUCKETS);
+
+    let lat: Vec<f64> = buckets.iter().filter_map(|b| b.avg_ms).collect();
+    if lat.is_empty() {
+        return String::new();
+    }
+    let min = lat.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = lat.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = (max - min).max(0.5);  // flat latency → line sits at the bottom gridline
+
+    let x = |i: usize| LEFT + (i as f64 + 0.5) * (W - LEFT) / BUCKETS as f64;
+    let y = |v: f64| BOTTOM - (v - min) / range * (BOTTOM - TOP);
+
+    let mut svg = format!(
+        r#"<svg class="latency-chart" viewBox="0 0 {W} {H}" width="100%" role="img" aria-label="Latency, last {h}h">"#,
+        h = minutes / 60,
+    );
+
+    // min/max gridlines with labels
+    for (v, label) in [(max, "max"), (min, "min")] {
+        let gy = y(v);
+        write!(svg,
+            r#"<line x1="{LEFT}" y1="{gy:.1}" x2="{W}" y2="{gy:.1}" style="stroke:var(--colorNeutralStroke2);stroke-dasharray:3 3"/><text x="{tx}" y="{ty:.1}" text-anchor="end" style="fill:var(--colorNeutralForeground3);font-size:9px">{label} {v:.0}ms</text>"#,
+            tx = LEFT - 4.0, ty = gy + 3.0,
+        ).unwrap();
+        if range <= 0.5 { break; }  // single gridline when flat
+    }
+
+    // Time axis: five ticks across the window
+    for i in 0..=4 {
+        let t = from + (to - from) * i / 4;
+        let tx = LEFT + (W - LEFT) * i as f64 / 4.0;
+        let anchor = match i { 0 => "start", 4 => "end", _ => "middle" };
+        let label = cfg.fmt_time(t, "%H:%M");
+        write!(svg,
+            r#"<text x="{tx:.1}" y="{ty}" text-anchor="{anchor}" style="fill:var(--colorNeutralForeground3);font-size:9px">{label}</text>"#,
+            ty = H - 4.0,
+        ).unwrap();
+    }
+
+    // One polyline per run of consecutive buckets with data
+    let mut run: Vec<(f64, f64)> = Vec::new();
+    let flush = |run: &mut Vec<(f64, f64)>, svg: &mut String| {
+        match run.len() {
+            0 => {}
+            1 => write!(svg, r#"<circle cx="{:.1}" cy="{:.1}" r="1.5" style="fill:var(--colorBrandForeground1)"/>"#, run[0].0, run[0].1).unwrap(),
+            _ => {
+                let pts: Vec<String> = run.iter().map(|(px, py)| format!("{px:.1},{py:.1}")).collect();
+                write!(svg, r#"<polyline points="{}" style="fill:none;stroke:var(--colorBrandForeground1);stroke-width:1.5"/>"#, pts.join(" ")).unwrap();
             }
         }
-        title = format!(
-            "{} checks · avg {avg:.0}ms ±{stddev:.0} · min {min:.0}ms · max {max:.0}ms",
-            checks.len()
-        );
+        run.clear();
+    };
+    for (i, b) in buckets.iter().enumerate() {
+        match b.avg_ms {
+            Some(v) => run.push((x(i), y(v))),
+            None    => flush(&mut run, &mut svg),
+        }
     }
+    flush(&mut run, &mut svg);
 
-    format!(r#"{pad_str}<span class="spark" title="{title}">{{{values}}}</span>"#)
+    write!(svg, "<title>{} buckets of {}m</title></svg>", BUCKETS, width / 60).unwrap();
+    svg
 }
 
 // --- Tier / status helpers ---
 
-pub fn tier_class(uptime_pct: Option<f64>) -> &'static str {
+pub fn tier_class(uptime_pct: Option<f64>, tiers: &Tiers) -> &'static str {
     match uptime_pct {
-        Some(p) if p >= 100.0 => "tier-perfect",
-        Some(p) if p >= 99.0  => "tier-good",
-        Some(p) if p >= 95.0  => "tier-degraded",
-        Some(p) if p > 0.0    => "tier-critical",
-        _                     => "tier-down",
+        Some(p) if p >= tiers.perfect  => "tier-perfect",
+        Some(p) if p >= tiers.good     => "tier-good",
+        Some(p) if p >= tiers.degraded => "tier-degraded",
+        Some(p) if p > 0.0             => "tier-critical",
+        Some(_)                        => "tier-down",
+        None                           => "tier-pending",
     }
 }
 
 pub fn state_tier(status: &str) -> &'static str {
     match status {
-        "UP"   => "tier-good",
-        "DOWN" => "tier-down",
-        _      => "tier-neutral",
+        "UP"            => "tier-good",
+        "DOWN"          => "tier-down",
+        "MISCONFIGURED" => "tier-misconfigured",
+        NO_DATA | PENDING => "tier-pending",
+        _               => "tier-neutral",
     }
 }
 
@@ -599,182 +2422,392 @@ pub fn get_icon_svg(key: &str) -> &'static str {
     }
 }
 
+pub fn find_icon(key: &str) -> Option<&'static Icon> {
+    ICONS.iter().find(|i| i.key == key)
+}
+
+/// Cache-busted URL for a service icon; unknown keys get the fallback, as inline.
+pub fn icon_url(key: &str) -> String {
+    let icon = find_icon(key).or_else(|| find_icon("fallback")).expect("fallback icon");
+    format!("/icons/{}?v={}", icon.key, icon.hash)
+}
+
 // --- HTML rendering ---
 
 pub fn render_stats_section(
-    w5m: &WindowStats, w1h: &WindowStats, w24h: &WindowStats, w7d: &WindowStats,
+    windows: &[StatsWindow], stats: &[WindowStats],
     pings_label: &str, time_col_label: &str, detail_rows: &str,
 ) -> String {
-    let loss_5m  = w5m.uptime_pct.map(|u| 100.0 - u);
-    let loss_1h  = w1h.uptime_pct.map(|u| 100.0 - u);
-    let loss_24h = w24h.uptime_pct.map(|u| 100.0 - u);
-    let loss_7d  = w7d.uptime_pct.map(|u| 100.0 - u);
+    let mut head_cells   = String::new();
+    let mut uptime_cells = String::new();
+    let mut avg_cells    = String::new();
+    let mut min_cells    = String::new();
+    let mut max_cells    = String::new();
+    let mut p95_cells    = String::new();
+    let mut p99_cells    = String::new();
+    let mut jitter_cells = String::new();
+    let mut loss_cells   = String::new();
+    for (w, s) in windows.iter().zip(stats) {
+        let checks = if s.sample_count == 1 { "check" } else { "checks" };
+        write!(head_cells, r#"<th title="based on {} {checks}">{}</th>"#, fmt_count(s.sample_count), html_escape(&w.label)).unwrap();
+        write!(uptime_cells, "<td>{}</td>", fmt_pct(s.uptime_pct)).unwrap();
+        write!(avg_cells, "<td>{}</td>", fmt_ms(s.avg_ms)).unwrap();
+        write!(min_cells, "<td>{}</td>", fmt_ms(s.min_ms)).unwrap();
+        write!(max_cells, "<td>{}</td>", fmt_ms(s.max_ms)).unwrap();
+        write!(p95_cells, "<td>{}</td>", fmt_ms(s.p95_ms)).unwrap();
+        write!(p99_cells, "<td>{}</td>", fmt_ms(s.p99_ms)).unwrap();
+        write!(jitter_cells, "<td>{}</td>", fmt_ms(s.jitter_ms)).unwrap();
+        write!(loss_cells, "<td>{}</td>", fmt_pct(s.uptime_pct.map(|u| 100.0 - u))).unwrap();
+    }
     format!(
         include_str!("templates/stats_section.html"),
-        uptime_5m  = fmt_pct(w5m.uptime_pct),
-        uptime_1h  = fmt_pct(w1h.uptime_pct),
-        uptime_24h = fmt_pct(w24h.uptime_pct),
-        uptime_7d  = fmt_pct(w7d.uptime_pct),
-        avg_5m  = fmt_ms(w5m.avg_ms),
-        avg_1h  = fmt_ms(w1h.avg_ms),
-        avg_24h = fmt_ms(w24h.avg_ms),
-        avg_7d  = fmt_ms(w7d.avg_ms),
-        min_5m  = fmt_ms(w5m.min_ms),
-        min_1h  = fmt_ms(w1h.min_ms),
-        min_24h = fmt_ms(w24h.min_ms),
-        min_7d  = fmt_ms(w7d.min_ms),
-        max_5m  = fmt_ms(w5m.max_ms),
-        max_1h  = fmt_ms(w1h.max_ms),
-        max_24h = fmt_ms(w24h.max_ms),
-        max_7d  = fmt_ms(w7d.max_ms),
-        loss_5m  = fmt_pct(loss_5m),
-        loss_1h  = fmt_pct(loss_1h),
-        loss_24h = fmt_pct(loss_24h),
-        loss_7d  = fmt_pct(loss_7d),
+        head_cells   = head_cells,
+        uptime_cells = uptime_cells,
+        avg_cells    = avg_cells,
+        min_cells    = min_cells,
+        max_cells    = max_cells,
+        p95_cells    = p95_cells,
+        p99_cells    = p99_cells,
+        jitter_cells = jitter_cells,
+        loss_cells   = loss_cells,
         pings_label = pings_label,
         time_col_label = time_col_label,
         detail_rows = detail_rows,
     )
 }
 
-pub fn render_host(db: &Connection, host: &Host, user_open: Option<bool>) -> String {
-    let (w5m, w1h, w24h, w7d) = query_all_window_stats(db, &host.addr);
+/// "ack" badge with the note as tooltip, for a DOWN target whose current outage is
+/// acknowledged in config.
+fn ack_badge(db: &Connection, cfg: &Config, key: &str, status: &str) -> String {
+    if status != "DOWN" || !cfg.acks.iter().any(|a| a.key == key) {
+        return String::new();
+    }
+    match query_down_since(db, key).and_then(|since| query_ack(db, cfg, key, &since)) {
+        Some(note) => format!(
+            r#" <span class="ack-badge" title="Acknowledged — {}">ack</span>"#,
+            html_escape(note),
+        ),
+        None => String::new(),
+    }
+}
+
+/// Header badge for a paused host: no latency, no uptime, no dot.
+const PAUSED_BADGE: &str = r#"<span class="host-badge-group"><span class="svc-latency"></span><span class="streak tier-neutral" title="Paused — not polled">paused</span><span class="svc-status unknown">–</span></span>"#;
+
+/// Header badge for a host that hasn't been checked yet — pending, not DOWN.
+const PENDING_BADGE: &str = r#"<span class="host-badge-group"><span class="svc-latency"></span><span class="streak tier-pending" title="Not seen UP yet">pending</span><span class="svc-status pending">…</span></span>"#;
+
+/// "Last N checks" rows, newest first. A failing newest row carries the latest
+/// round's failure reason in place of the (absent) latency.
+fn render_detail_rows(cfg: &Config, rows: &[(String, String, Option<f64>)], error: Option<&str>) -> String {
+    let mut html = String::new();
+    for (i, (ts, status, latency)) in rows.iter().enumerate() {
+        let time = cfg.fmt_stored(ts, "%H:%M:%S%.3f");
+        let (dot_class, dot_char) = match status.as_str() {
+            "UP"   => ("status-up",   "✓"),
+            "DOWN" => ("status-down", "✗"),
+            PENDING => ("",           "…"),
+            _      => ("",            "–"),
+        };
+        let middle = match (latency, error) {
+            (Some(v), _) => format!("{v:.1}ms"),
+            (None, Some(e)) if i == 0 && status != "UP" => format!(r#"<span class="check-error">{status} — {}</span>"#, html_escape(e)),
+            _ => String::new(),
+        };
+        write!(html, r#"<div class="pg-row"><span>{time}</span><span>{middle}</span><span class="{dot_class}">{dot_char}</span></div>"#).unwrap();
+    }
+    html
+}
+
+/// `chart` adds the 24h latency chart (always on the live page; `include_charts` in email).
+/// `resolved_ip`: the address last pinged, for a host given by name.
+/// `started_at`: process start, for the "Since start" column — the live page only.
+#[allow(clippy::too_many_arguments)]
+pub fn render_host(db: &Connection, cfg: &Config, host: &Host, user_open: Option<bool>, resolved_ip: Option<&str>, errors: &HashMap<String, String>, chart: bool, started_at: Option<&str>) -> String {
+    if !host.members.is_empty() {
+        return render_host_group(db, cfg, host, user_open, errors);
+    }
+    let stats = query_all_window_stats(db, &host.addr, &cfg.stats_windows);
+    let uptime_1h = uptime_1h(db, &host.addr, &cfg.stats_windows, &stats);
     let (cur_status, latency) = query_latest_status(db, &host.addr);
     let tier = state_tier(&cur_status);
     let latency_str = latency.map_or_else(String::new, |ms| format!("{ms:.0}ms"));
-    let rows = query_recent_checks(db, &host.addr, 40);
-    let spark_str = fmt_sparkline(&rows);
+    let rows = query_recent_checks(db, &host.addr, cfg.host_detail_rows.max(SPARK_BARS) as i64);
+    let spark_str = render_sparkline(db, cfg, &host.addr, Some(&rows));
     let (dot_class, dot_char) = match cur_status.as_str() {
         "UP"   => ("up",      "✓"),
         "DOWN" => ("down",    "✗"),
         _      => ("unknown", "–"),
     };
-    let uptime_pct = fmt_pct(w1h.uptime_pct);
-    let streak_display = format!(
-        r#"<span class="host-badge-group"><span class="svc-latency">{spark_str}{latency_str}</span><span class="streak {tier}" title="1h uptime: {uptime_pct}">{uptime_pct}</span><span class="svc-status {dot_class}">{dot_char}</span></span>"#,
-    );
+    let uptime_pct = fmt_pct(uptime_1h);
+    let streak_display = if host.paused {
+        PAUSED_BADGE.to_string()
+    } else if is_pending(&cur_status) {
+        PENDING_BADGE.to_string()
+    } else {
+        format!(
+            r#"<span class="host-badge-group"><span class="svc-latency">{spark_str}{latency_str}</span><span class="streak {tier}" title="1h uptime: {uptime_pct}">{uptime_pct}</span><span class="svc-status {dot_class}">{dot_char}</span></span>"#,
+        )
+    };
+
+    let all_up_1h = host.paused || uptime_1h.is_none_or(|p| p >= 100.0);
+    let open_attr = match user_open {
+        Some(true)  => " open",
+        Some(false) => "",
+        None        => if all_up_1h { "" } else { " open" },
+    };
+
+    let shown = rows.len().min(cfg.host_detail_rows);
+    let detail_rows = render_detail_rows(cfg, &rows[..shown], errors.get(&host.addr).map(String::as_str));
+    let pings_label = format!("Last {} {}", cfg.host_detail_rows, if cfg.host_detail_rows == 1 { "ping" } else { "pings" });
+    let (windows, stats) = with_since_start(db, cfg, &host.addr, &stats, started_at);
+    let stats_section = render_stats_section(&windows, &stats, &pings_label, "Time", &detail_rows);
 
-    let all_up_1h = w1h.uptime_pct.map_or(true, |p| p >= 100.0);
+    let chart_html = if chart { render_latency_chart(db, cfg, &host.addr, 1440) } else { String::new() };
+    let resolved_ip_html = match resolved_ip {
+        Some(ip) => format!(r#" · <span class="ip">{ip}</span>"#),
+        None => String::new(),
+    };
+
+    format!(
+        include_str!("templates/host.html"),
+        paused_class = if host.paused { " paused" } else { "" },
+        open_attr = open_attr,
+        label = format!("{}{}", html_escape(&host.label), ack_badge(db, cfg, &host.addr, &cur_status)),
+        addr = html_escape(&host.addr),
+        resolved_ip_html = resolved_ip_html,
+        streak_display = streak_display,
+        chart_html = chart_html,
+        stats_section = stats_section,
+    )
+}
+
+/// Group card: one row per member with its own sparkline, latency and 1h uptime; the
+/// header rolls them up like `render_service_card` does — up/total badge coloured by
+/// the members' combined 1h uptime, dot UP when all (or any) members are UP.
+pub fn render_host_group(db: &Connection, cfg: &Config, host: &Host, user_open: Option<bool>, errors: &HashMap<String, String>) -> String {
+    let statuses: Vec<(String, Option<f64>)> = host.members.iter()
+        .map(|m| query_latest_status(db, m))
+        .collect();
+    let up_count = statuses.iter().filter(|(s, _)| s == "UP").count();
+    let total = host.members.len();
+    let card_uptime = query_card_uptime(db, &host.members, 60);
+    let tier = tier_class(card_uptime, &cfg.tiers);
+    let card_up = match host.require {
+        Require::All => up_count == total,
+        Require::Any => up_count > 0,
+    };
+    let (dot_class, dot_char) = if statuses.iter().all(|(s, _)| s != "UP" && s != "DOWN") {
+        ("unknown", "–")
+    } else if card_up {
+        ("up", "✓")
+    } else {
+        ("down", "✗")
+    };
+    let rule = match host.require { Require::All => "all", Require::Any => "any" };
+    let streak_display = if host.paused {
+        PAUSED_BADGE.to_string()
+    } else if statuses.iter().all(|(s, _)| is_pending(s)) {
+        PENDING_BADGE.to_string()
+    } else {
+        format!(
+            r#"<span class="host-badge-group"><span class="svc-latency"></span><span class="streak {tier}" title="1h uptime: {uptime}">{up_count}/{total}</span><span class="svc-status {dot_class}">{dot_char}</span></span>"#,
+            uptime = fmt_pct(card_uptime),
+        )
+    };
+
+    let all_up_1h = host.paused || card_uptime.is_none_or(|p| p >= 100.0);
     let open_attr = match user_open {
         Some(true)  => " open",
         Some(false) => "",
         None        => if all_up_1h { "" } else { " open" },
     };
 
-    let mut detail_rows = String::new();
-    for (ts, status, latency) in &rows[..rows.len().min(20)] {
-        let time = if ts.len() >= 23 { &ts[11..23] } else { ts.as_str() };
+    let mut member_rows = String::new();
+    for (addr, (status, latency)) in host.members.iter().zip(&statuses) {
+        let spark_str = render_sparkline(db, cfg, addr, None);
+        let uptime = fmt_pct(query_window_stats(db, addr, 60).uptime_pct);
         let (dot_class, dot_char) = match status.as_str() {
             "UP"   => ("status-up",   "✓"),
             "DOWN" => ("status-down", "✗"),
             _      => ("",            "–"),
         };
-        if let Some(v) = latency {
-            write!(detail_rows, r#"<div class="pg-row"><span>{time}</span><span>{v:.1}ms</span><span class="{dot_class}">{dot_char}</span></div>"#).unwrap();
-        } else {
-            write!(detail_rows, r#"<div class="pg-row"><span>{time}</span><span></span><span class="{dot_class}">{dot_char}</span></div>"#).unwrap();
-        }
+        let reason = match errors.get(addr) {
+            Some(e) if status == "DOWN" => format!(r#" · <span class="check-error">{}</span>"#, html_escape(e)),
+            _ => String::new(),
+        };
+        write!(member_rows,
+            r#"<div class="pg-row"><span>{addr}{ack}</span><span><span class="svc-latency">{spark_str}{lat}</span> · {uptime}{reason}</span><span class="{dot_class}">{dot_char}</span></div>"#,
+            addr = html_escape(addr), ack = ack_badge(db, cfg, addr, status), lat = fmt_latency(*latency),
+        ).unwrap();
     }
-    let stats_section = render_stats_section(&w5m, &w1h, &w24h, &w7d, "Last 20 pings", "Time", &detail_rows);
+    let stats_section = format!(
+        r#"<div class="pings-header">Members — {rule} must be up</div><div class="pings-grid">{member_rows}</div>"#,
+    );
 
     format!(
         include_str!("templates/host.html"),
+        paused_class = if host.paused { " paused" } else { "" },
         open_attr = open_attr,
-        label = host.label,
-        addr = host.addr,
+        label = html_escape(&host.label),
+        addr = html_escape(&host.addr),
+        resolved_ip_html = "",
         streak_display = streak_display,
+        chart_html = "",
         stats_section = stats_section,
     )
 }
 
-pub fn render_service_item(db: &Connection, svc: &Service, id: &str, user_open: Option<bool>, resolved_ip: Option<&str>, cur_status: &str, latency: Option<f64>) -> String {
+#[allow(clippy::too_many_arguments)]
+pub fn render_service_item(db: &Connection, cfg: &Config, svc: &Service, id: &str, user_open: Option<bool>, resolved_ip: Option<&str>, error: Option<&str>, cur_status: &str, latency: Option<f64>, inline_icons: bool, chart: bool, started_at: Option<&str>) -> String {
     let key = format!("svc:{}", svc.label);
+    let suspicious = svc.too_fast(cur_status, latency);
     let (dot_class, dot_char) = match cur_status {
-        "UP"   => ("up",      "✓"),
-        "DOWN" => ("down",    "✗"),
-        _      => ("unknown", "–"),
+        _ if suspicious => ("suspicious",    "!"),
+        "UP"            => ("up",            "✓"),
+        "DOWN"          => ("down",          "✗"),
+        "MISCONFIGURED" => ("misconfigured", "?"),
+        NO_DATA | PENDING => ("pending",     "…"),
+        _               => ("unknown",       "–"),
     };
     let icon_html = if let Some(data) = &svc.icon_data {
-        format!(r#"<img style="width:20px;height:20px" src="{data}">"#)
-    } else {
+        format!(r#"<img style="width:20px;height:20px" src="{}">"#, html_escape(data))
+    } else if inline_icons {
         get_icon_svg(&svc.icon).to_string()
+    } else {
+        format!(r#"<img src="{}" alt="">"#, icon_url(&svc.icon))
     };
     let latency_str = fmt_latency(latency);
 
-    let (w5m, w1h, w24h, w7d) = query_all_window_stats(db, &key);
-    let tier = state_tier(cur_status);
-    let uptime_badge = fmt_pct(w1h.uptime_pct);
-    let streak_title = format!("1h uptime: {uptime_badge}");
+    let stats = query_all_window_stats(db, &key, &cfg.stats_windows);
+    let tier = if suspicious { "tier-suspicious" } else { state_tier(cur_status) };
+    let (uptime_badge, mut streak_title) = if svc.paused {
+        ("paused".to_string(), "Paused — not polled".to_string())
+    } else if is_pending(cur_status) {
+        ("pending".to_string(), "Not seen UP yet".to_string())
+    } else if suspicious {
+        let pct = fmt_pct(uptime_1h(db, &key, &cfg.stats_windows, &stats));
+        let title = format!("Suspiciously fast — under min_latency_ms ({}ms); intercepted? · 1h uptime: {pct}",
+            svc.min_latency_ms.unwrap_or_default());
+        (pct, title)
+    } else {
+        let pct = fmt_pct(uptime_1h(db, &key, &cfg.stats_windows, &stats));
+        let title = format!("1h uptime: {pct}");
+        (pct, title)
+    };
+    let cert_expiry = if svc.check == "tls" { query_cert_expiry(db, &key) } else { None };
+    if let Some(expiry) = cert_expiry {
+        let days = (expiry - Local::now().timestamp()) / 86400;
+        write!(streak_title, " · certificate expires {} ({days}d)", cfg.fmt_time(expiry, "%Y-%m-%d")).unwrap();
+    }
     let open_attr = if user_open.unwrap_or(false) { " open" } else { "" };
 
-    let recent = query_recent_checks(db, &key, 40);
-    let spark_str = fmt_sparkline(&recent);
-    let mut detail_rows = String::new();
-    for (ts, s, lat) in &recent[..recent.len().min(10)] {
-        let time = if ts.len() >= 23 { &ts[11..23] } else { ts.as_str() };
-        let (dot_class, dot_char) = match s.as_str() {
-            "UP"   => ("status-up",   "✓"),
-            "DOWN" => ("status-down", "✗"),
-            _      => ("",            "–"),
-        };
-        if let Some(v) = lat {
-            write!(detail_rows, r#"<div class="pg-row"><span>{time}</span><span>{v:.1}ms</span><span class="{dot_class}">{dot_char}</span></div>"#).unwrap();
-        } else {
-            write!(detail_rows, r#"<div class="pg-row"><span>{time}</span><span></span><span class="{dot_class}">{dot_char}</span></div>"#).unwrap();
-        }
-    }
-    let stats_section = render_stats_section(&w5m, &w1h, &w24h, &w7d, "Last 10 checks", "Time", &detail_rows);
+    let recent = query_recent_checks(db, &key, cfg.service_detail_rows.max(SPARK_BARS) as i64);
+    let spark_str = render_sparkline(db, cfg, &key, Some(&recent));
+    let detail_rows = render_detail_rows(cfg, &recent[..recent.len().min(cfg.service_detail_rows)], error);
+    let checks_label = format!("Last {} {}", cfg.service_detail_rows, if cfg.service_detail_rows == 1 { "check" } else { "checks" });
+    let (windows, stats) = with_since_start(db, cfg, &key, &stats, started_at);
+    let stats_section = render_stats_section(&windows, &stats, &checks_label, "Time", &detail_rows);
+    // With several targets, the poll loop records which one answered instead of an IP.
     let resolved_ip_html = match resolved_ip {
+        Some(via) if svc.targets.len() > 1 => format!(r#" · via <span class="ip">{}</span>"#, html_escape(via)),
         Some(ip) => format!(r#" · <span class="ip">{ip}</span>"#),
         None => String::new(),
     };
+    let chart_html = if chart { render_latency_chart(db, cfg, &key, 1440) } else { String::new() };
 
     format!(
         include_str!("templates/service_item.html"),
+        paused_class = if svc.paused { " paused" } else { "" },
         id = id,
         open_attr = open_attr,
         icon_html = icon_html,
         dot_class = dot_class,
         dot_char = dot_char,
-        label = svc.label,
+        label = format!("{}{}", html_escape(&svc.label), ack_badge(db, cfg, &key, cur_status)),
         latency_str = latency_str,
         spark_str = spark_str,
         tier = tier,
         uptime_badge = uptime_badge,
         streak_title = streak_title,
-        check = svc.check,
-        target = svc.target,
+        check = html_escape(&svc.check),
+        target = if svc.check == "quorum" {
+            html_escape(&format!("{} of {}", svc.quorum_needed(), svc.members.join(", ")))
+        } else if svc.check == "exec" {
+            html_escape(&svc.command.join(" "))
+        } else if let (Some(name), "dns") = (&svc.query_name, svc.check.as_str()) {
+            format!("{} {} {}", html_escape(&svc.targets.join(", ")), html_escape(name), dns_record_name(svc.dns_question(svc.target()).1))
+        } else if let (Some(record), "dns") = (&svc.record, svc.check.as_str()) {
+            format!("{} {}", html_escape(&svc.targets.join(", ")), html_escape(&record.to_uppercase()))
+        } else {
+            html_escape(&svc.targets.join(", "))
+        },
         resolved_ip_html = resolved_ip_html,
+        chart_html = chart_html,
         stats_section = stats_section,
     )
 }
 
-pub fn render_service_card(db: &Connection, title: &str, svcs: &[&Service], start_idx: usize, open: bool, open_svc_items: Option<&HashSet<String>>, resolved_ips: &HashMap<String, Option<String>>) -> String {
+#[allow(clippy::too_many_arguments)]
+pub fn render_service_card(db: &Connection, cfg: &Config, title: &str, svcs: &[&Service], start_idx: usize, open: bool, open_svc_items: Option<&HashSet<String>>, resolved_ips: &HashMap<String, Option<String>>, errors: &HashMap<String, String>, inline_icons: bool, charts: bool, started_at: Option<&str>) -> String {
     if svcs.is_empty() {
         return String::new();
     }
 
     // Single pass: fetch status+latency for all services — used for both the
     // UP/DOWN badge count and passed through to each item renderer (no double query).
+    // Unknown check types are never polled; they report MISCONFIGURED rather than a
+    // stale or missing status so a config typo doesn't read as an outage.
     let statuses: Vec<(String, Option<f64>)> = svcs.iter()
-        .map(|svc| query_latest_status(db, &format!("svc:{}", svc.label)))
+        .map(|svc| if svc.paused {
+            ("PAUSED".to_string(), None)
+        } else if cfg.is_runnable(svc) {
+            query_latest_status(db, &format!("svc:{}", svc.label))
+        } else {
+            ("MISCONFIGURED".to_string(), None)
+        })
         .collect();
 
-    let up_count = statuses.iter().filter(|(s, _)| s == "UP").count();
-    let total = svcs.len();
-    let keys: Vec<String> = svcs.iter().map(|s| format!("svc:{}", s.label)).collect();
+    // Paused and informational services are shown but don't count towards the card's rollup.
+    let counted: Vec<&(String, Option<f64>)> = svcs.iter().zip(&statuses)
+        .filter(|(svc, _)| svc.counted())
+        .map(|(_, status)| status)
+        .collect();
+    let up_count = counted.iter().filter(|(s, _)| s == "UP").count();
+    let pending_count = counted.iter().filter(|(s, _)| is_pending(s)).count();
+    let total = counted.len();
+    let keys: Vec<String> = svcs.iter().filter(|s| s.counted()).map(|s| format!("svc:{}", s.label)).collect();
     let card_uptime = query_card_uptime(db, &keys, 60);
-    let tier = tier_class(card_uptime);
-    let title_attr = match card_uptime {
+    let tier = tier_class(card_uptime, &cfg.tiers);
+    let degraded = match (cfg.degraded_loss_pct, card_uptime) {
+        (Some(limit), Some(u)) => 100.0 - u > limit,
+        _ => false,
+    };
+    let mut title_attr = match card_uptime {
         Some(_) => format!("1h uptime: {}", fmt_pct(card_uptime)),
         None    => "No data".to_string(),
     };
+    if degraded {
+        let worst = svcs.iter()
+            .filter(|svc| svc.counted())
+            .filter_map(|svc| query_window_stats(db, &format!("svc:{}", svc.label), 60).uptime_pct.map(|u| (svc, u)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((svc, u)) = worst {
+            write!(title_attr, " · worst: {} {}", html_escape(&svc.label), fmt_pct(Some(u))).unwrap();
+        }
+    }
     let (card_dot_class, card_dot_char) = if total == 0 {
         ("unknown", "–")
-    } else if up_count == total {
-        ("up", "✓")
-    } else {
+    } else if statuses.iter().all(|(s, _)| s == "MISCONFIGURED") {
+        ("misconfigured", "?")
+    } else if pending_count == total {
+        ("pending", "…")
+    } else if up_count + pending_count < total {
         ("down", "✗")
+    } else if degraded {
+        ("degraded", "!")
+    } else {
+        ("up", "✓")
     };
     let center_html = format!(
         r#"<span class="svc-card-center"><span class="streak svc-card-count {tier}" title="{title_attr}">{up_count}/{total}</span></span>"#
@@ -786,7 +2819,7 @@ pub fn render_service_card(db: &Connection, title: &str, svcs: &[&Service], star
     let open_attr = if open { " open" } else { "" };
     let mut html = format!(
         include_str!("templates/service_card.html"),
-        title      = title,
+        title      = html_escape(title),
         center_html = center_html,
         right_html  = right_html,
         open_attr  = open_attr,
@@ -795,28 +2828,119 @@ pub fn render_service_card(db: &Connection, title: &str, svcs: &[&Service], star
         let id = format!("svc-{}", start_idx + i);
         let item_open = open_svc_items.map(|set| set.contains(&id));
         let resolved_ip = resolved_ips.get(&svc.label).and_then(|o| o.as_deref());
-        html.push_str(&render_service_item(db, svc, &id, item_open, resolved_ip, cur_status, *latency));
+        let error = errors.get(&format!("svc:{}", svc.label)).map(String::as_str);
+        html.push_str(&render_service_item(db, cfg, svc, &id, item_open, resolved_ip, error, cur_status, *latency, inline_icons, charts, started_at));
     }
     html.push_str("</div></details>");
     html
 }
 
-pub fn render_services(db: &Connection, services: &[Service], ui: &UiCookie, resolved_ips: &HashMap<String, Option<String>>) -> String {
-    if services.is_empty() {
-        return String::new();
+/// Services split into cards, in render order, each with the index its item ids
+/// (`svc-N`) start from. By `group` when any service has one, else by check type.
+fn group_services(services: &[Service]) -> Vec<(&str, Vec<&Service>, usize)> {
+    if services.iter().any(|s| s.group.is_some()) {
+        let mut groups: Vec<(&str, Vec<&Service>)> = Vec::new();
+        for svc in services {
+            let title = svc.group.as_deref().unwrap_or(DEFAULT_GROUP);
+            match groups.iter_mut().find(|(t, _)| *t == title) {
+                Some((_, svcs)) => svcs.push(svc),
+                None => groups.push((title, vec![svc])),
+            }
+        }
+        // Stable: named groups keep config order, the default card goes last.
+        groups.sort_by_key(|(t, _)| *t == DEFAULT_GROUP);
+        let mut start_idx = 0;
+        return groups.into_iter().map(|(title, svcs)| {
+            let idx = start_idx;
+            start_idx += svcs.len();
+            (title, svcs, idx)
+        }).collect();
     }
-
     let mut web: Vec<&Service>  = Vec::new();
     let mut icmp: Vec<&Service> = Vec::new();
     let mut dns: Vec<&Service>  = Vec::new();
+    let mut custom: Vec<&Service> = Vec::new();
+    let mut misconfigured: Vec<&Service> = Vec::new();
+    let mut overall: Vec<&Service> = Vec::new();
     for svc in services {
         match svc.check.as_str() {
-            "tcp"  => web.push(svc),
-            "ping" => icmp.push(svc),
-            "dns"  => dns.push(svc),
-            _      => {}
+            "tcp" | "http" | "tls" => web.push(svc),
+            "ping"   => icmp.push(svc),
+            "dns"    => dns.push(svc),
+            "exec"   => custom.push(svc),
+            "quorum" => overall.push(svc),
+            _        => misconfigured.push(svc),
+        }
+    }
+    // Quorum lights go first — they're the summary. Their ids come after everything
+    // else so adding one doesn't shift the saved open state of existing items.
+    let icmp_idx = web.len();
+    let dns_idx = icmp_idx + icmp.len();
+    let custom_idx = dns_idx + dns.len();
+    let misconfigured_idx = custom_idx + custom.len();
+    let overall_idx = misconfigured_idx + misconfigured.len();
+    vec![
+        ("Overall", overall, overall_idx),
+        ("Web", web, 0),
+        ("ICMP", icmp, icmp_idx),
+        ("DNS", dns, dns_idx),
+        ("Custom", custom, custom_idx),
+        ("Misconfigured", misconfigured, misconfigured_idx),
+    ]
+}
+
+/// UI state with every host, card and service item expanded — the email view, and
+/// the live page's `?open=all`.
+pub fn all_open_ui(cfg: &Config) -> UiCookie {
+    UiCookie {
+        open_hosts: Some(cfg.hosts.iter().map(|h| h.addr.clone()).collect()),
+        open_svc_cards: None,  // None = all open (no cookie state)
+        open_svc_items: Some((0..cfg.services.len()).map(|i| format!("svc-{i}")).collect()),
+        theme: None,
+        services_open: true,
+        colorblind: false,
+    }
+}
+
+/// UI state with only unhealthy hosts and services expanded, plus the cards holding
+/// them — the live page's `?open=down`.
+pub fn down_open_ui(db: &Connection, cfg: &Config) -> UiCookie {
+    let is_down = |key: &str| query_latest_status(db, key).0 == "DOWN";
+    let open_hosts = cfg.hosts.iter()
+        .filter(|h| !h.paused && h.targets().iter().any(|t| is_down(t)))
+        .map(|h| h.addr.clone())
+        .collect();
+    let mut open_svc_cards = HashSet::new();
+    let mut open_svc_items = HashSet::new();
+    for (title, svcs, start_idx) in group_services(&cfg.services) {
+        for (i, svc) in svcs.iter().enumerate() {
+            let unhealthy = !CHECK_TYPES.contains(&svc.check.as_str())
+                || (!svc.paused && is_down(&format!("svc:{}", svc.label)));
+            if unhealthy {
+                open_svc_cards.insert(title.to_string());
+                open_svc_items.insert(format!("svc-{}", start_idx + i));
+            }
         }
     }
+    UiCookie {
+        open_hosts: Some(open_hosts),
+        open_svc_cards: Some(open_svc_cards),
+        open_svc_items: Some(open_svc_items),
+        theme: None,
+        services_open: true,
+        colorblind: false,
+    }
+}
+
+/// `inline_icons` embeds each icon in the markup (self-contained email) instead of
+/// linking the cacheable `/icons/<key>` route; `charts` adds each service's 24h
+/// latency chart (email with `include_charts`).
+#[allow(clippy::too_many_arguments)]
+pub fn render_services(db: &Connection, cfg: &Config, services: &[Service], ui: &UiCookie, resolved_ips: &HashMap<String, Option<String>>, errors: &HashMap<String, String>, inline_icons: bool, charts: bool, started_at: Option<&str>) -> String {
+    if services.is_empty() {
+        return String::new();
+    }
+
     let svc_open = |title: &str| -> bool {
         match &ui.open_svc_cards {
             None => true,
@@ -825,25 +2949,205 @@ pub fn render_services(db: &Connection, services: &[Service], ui: &UiCookie, res
     };
 
     let open_items = ui.open_svc_items.as_ref();
-    let mut html = render_service_card(db, "Web", &web, 0, svc_open("Web"), open_items, resolved_ips);
-    html.push_str(&render_service_card(db, "ICMP", &icmp, web.len(), svc_open("ICMP"), open_items, resolved_ips));
-    html.push_str(&render_service_card(db, "DNS", &dns, web.len() + icmp.len(), svc_open("DNS"), open_items, resolved_ips));
+    let section_open = if ui.services_open { " open" } else { "" };
+    let mut html = format!(
+        r#"<details class="services-section"{section_open}><summary class="services-summary">Services</summary>"#,
+    );
+    for (title, svcs, start_idx) in group_services(services) {
+        html.push_str(&render_service_card(db, cfg, title, &svcs, start_idx, svc_open(title), open_items, resolved_ips, errors, inline_icons, charts, started_at));
+    }
+    html.push_str("</details>");
     html
 }
 
+/// Page footer: the built-in attribution, the config's `footer_html` (trusted, not
+/// escaped) in its place, or nothing when `show_footer = false`.
+pub fn render_footer(cfg: &Config) -> String {
+    if !cfg.show_footer {
+        return String::new();
+    }
+    match &cfg.footer_html {
+        Some(html) => format!("<footer>{html}</footer>"),
+        None => format!(r##"<footer>Made with &#10084;&#65039; by <a href="mailto:david@connol.ly">David Connolly</a> &amp; <a href="https://claude.ai">Claude</a> &middot; <a href="https://github.com/slartibardfast/pi-glass">pi-glass v{VERSION}</a></footer>"##),
+    }
+}
+
 // --- Mailer helpers ---
 
+/// A CSV file attached to an email: (filename, contents).
+pub type CsvAttachment<'a> = (&'a str, &'a str);
+
+/// Sends `html` to `cfg.to` through the configured backend — the daily digest and
+/// the `email` alert channel alike.
+pub async fn send_mail(cfg: &MailerConfig, subject: &str, html: &str, csv: Option<CsvAttachment<'_>>) -> Result<(), String> {
+    match cfg.backend {
+        MailBackend::Mailgun => send_mailgun(cfg, subject, html, csv).await,
+        MailBackend::Smtp    => send_smtp(cfg, subject, html, csv).await,
+    }
+}
+
+async fn send_mailgun(cfg: &MailerConfig, subject: &str, html: &str, csv: Option<CsvAttachment<'_>>) -> Result<(), String> {
+    let url = format!("https://api.mailgun.net/v3/{}/messages", cfg.mailgun_domain);
+    let client = reqwest::Client::new();
+
+    let mut form = reqwest::multipart::Form::new()
+        .text("from",    cfg.from.clone())
+        .text("subject", subject.to_string())
+        .text("html",    html.to_string());
+
+    for recipient in &cfg.to {
+        form = form.text("to", recipient.clone());
+    }
+    if let Some((filename, contents)) = csv {
+        let part = reqwest::multipart::Part::text(contents.to_string())
+            .file_name(filename.to_string())
+            .mime_str("text/csv")
+            .map_err(|e| e.to_string())?;
+        form = form.part("attachment", part);
+    }
+
+    let resp = client
+        .post(&url)
+        .basic_auth("api", Some(&cfg.mailgun_api_key))
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        Err(format!("mailgun error {status}: {body}"))
+    }
+}
+
+/// Send over SMTP (`backend = "smtp"`) — e.g. straight to your own Postfix.
+async fn send_smtp(cfg: &MailerConfig, subject: &str, html: &str, csv: Option<CsvAttachment<'_>>) -> Result<(), String> {
+    use lettre::message::{header::ContentType, Attachment, MultiPart, SinglePart};
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+    let mut message = Message::builder()
+        .from(cfg.from.parse().map_err(|e| format!("from '{}': {e}", cfg.from))?)
+        .subject(subject);
+    for recipient in &cfg.to {
+        message = message.to(recipient.parse().map_err(|e| format!("to '{recipient}': {e}"))?);
+    }
+    let message = match csv {
+        None => message.header(ContentType::TEXT_HTML).body(html.to_string()),
+        Some((filename, contents)) => message.multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::html(html.to_string()))
+                .singlepart(Attachment::new(filename.to_string())
+                    .body(contents.to_string(), ContentType::parse("text/csv").unwrap())),
+        ),
+    }.map_err(|e| e.to_string())?;
+
+    let (builder, default_port) = match cfg.smtp_tls {
+        SmtpTls::Starttls => (AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&cfg.smtp_host), 587),
+        SmtpTls::Tls      => (AsyncSmtpTransport::<Tokio1Executor>::relay(&cfg.smtp_host), 465),
+        SmtpTls::None     => (Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&cfg.smtp_host)), 25),
+    };
+    let mut builder = builder.map_err(|e| e.to_string())?.port(cfg.smtp_port.unwrap_or(default_port));
+    if let (Some(user), Some(pass)) = (&cfg.username, &cfg.password) {
+        builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+    }
+    builder.build().send(message).await.map_err(|e| format!("smtp error: {e}"))?;
+    Ok(())
+}
+
+/// "Top issues" preamble for the email: the `n` polled targets with the lowest 24h
+/// uptime, with downtime estimated from their DOWN samples. Empty when all are 100%.
+pub fn render_top_issues(db: &Connection, cfg: &Config, n: usize) -> String {
+    let keys = cfg.hosts.iter().filter(|h| !h.paused).flat_map(|h| h.targets()).cloned()
+        .chain(cfg.services.iter()
+            .filter(|s| !s.paused && cfg.is_runnable(s))
+            .map(|s| format!("svc:{}", s.label)));
+    let mut worst: Vec<(String, f64, f64)> = keys
+        .filter_map(|key| {
+            let stats = query_window_stats(db, &key, 1440);
+            let uptime = stats.uptime_pct.filter(|&u| u < 100.0)?;
+            let down_samples = stats.sample_count as f64 * (100.0 - uptime) / 100.0;
+            let down_mins = down_samples * cfg.interval_for(&key) as f64 / 60.0;
+            Some((key, uptime, down_mins))
+        })
+        .collect();
+    if n == 0 || worst.is_empty() {
+        return String::new();
+    }
+    worst.sort_by(|a, b| a.1.total_cmp(&b.1));
+    worst.truncate(n);
+
+    let mut rows = String::new();
+    for (key, uptime, down_mins) in &worst {
+        write!(
+            rows,
+            r#"<tr><td>{}</td><td><span class="streak {}">{}</span></td><td>{:.0} min</td></tr>"#,
+            html_escape(&cfg.label_for(key)), tier_class(Some(*uptime), &cfg.tiers), fmt_pct(Some(*uptime)), down_mins,
+        ).unwrap();
+    }
+    format!(
+        r#"<div class="top-issues"><div class="top-issues-title">Top issues · last 24h</div><table><tr><th>Target</th><th>Uptime</th><th>Down</th></tr>{rows}</table></div>"#,
+    )
+}
+
+/// Incident log for the email: each outage of the last 24h, oldest first, with its
+/// start, end ("ongoing" if still DOWN) and duration. Built from the events table, so
+/// outages short enough to be debounced away don't appear. Empty when there were none.
+pub fn render_incidents(db: &Connection, cfg: &Config) -> String {
+    let now = Local::now().timestamp();
+    let mut events = query_events(db, None, now - 86400, 1000);
+    events.reverse();
+
+    // (key, start, end) — an UP whose DOWN predates the window starts `lasted_secs` before it.
+    let mut open: HashMap<String, i64> = HashMap::new();
+    let mut incidents: Vec<(String, i64, Option<i64>)> = Vec::new();
+    for e in events {
+        match e.status.as_str() {
+            "DOWN" => { open.insert(e.key, e.t); }
+            "UP" if e.previous == "DOWN" => {
+                let start = open.remove(&e.key).unwrap_or(e.t - e.lasted_secs);
+                incidents.push((e.key, start, Some(e.t)));
+            }
+            _ => {}
+        }
+    }
+    incidents.extend(open.into_iter().map(|(key, start)| (key, start, None)));
+    if incidents.is_empty() {
+        return String::new();
+    }
+    incidents.sort_by_key(|i| i.1);
+
+    let at = |t: i64| cfg.fmt_time(t, "%b %-d %H:%M");
+    let mut rows = String::new();
+    for (key, start, end) in &incidents {
+        let lasted = std::time::Duration::from_secs((end.unwrap_or(now) - start).max(0) as u64);
+        write!(
+            rows,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            html_escape(&cfg.label_for(key)), at(*start), end.map_or("ongoing".to_string(), at), fmt_duration(lasted),
+        ).unwrap();
+    }
+    format!(
+        r#"<div class="top-issues"><div class="top-issues-title">Incidents · last 24h</div><table><tr><th>Target</th><th>Start</th><th>End</th><th>Duration</th></tr>{rows}</table></div>"#,
+    )
+}
+
 /// Render the full page with all sections forced open (for email).
 pub fn render_full_page(db: &Connection, config: &Config) -> String {
-    let n = config.services.len();
-    let all_open_ui = UiCookie {
-        open_hosts: Some(config.hosts.iter().map(|h| h.addr.clone()).collect()),
-        open_svc_cards: None,  // None = all open (no cookie state)
-        open_svc_items: Some((0..n).map(|i| format!("svc-{i}")).collect()),
-        theme: None,
-    };
+    let all_open_ui = all_open_ui(config);
     let empty_ips: HashMap<String, Option<String>> = HashMap::new();
-    let services_html = render_services(db, &config.services, &all_open_ui, &empty_ips);
+    let no_errors: HashMap<String, String> = HashMap::new();
+    let top_issues = config.mailer.as_ref().map_or(default_top_issues(), |m| m.top_issues);
+    let charts = config.mailer.as_ref().is_some_and(|m| m.include_charts);
+    let services_html = format!(
+        "{}{}{}",
+        render_incidents(db, config),
+        render_top_issues(db, config, top_issues),
+        render_services(db, config, &config.services, &all_open_ui, &empty_ips, &no_errors, true, charts, None),
+    );
 
     let heading_html = if config.name == "pi-glass" {
         r#"<img src="/favicon.svg" style="height:var(--lineHeightHero700);width:var(--lineHeightHero700);display:block" alt="pi-glass">"#.to_string()
@@ -860,16 +3164,16 @@ pub fn render_full_page(db: &Connection, config: &Config) -> String {
         heading_html      = heading_html,
         style_head        = style_head,
         services_html     = services_html,
-        favicon_svg_route = "/favicon.svg",
+        status_icon_route = "/favicon.svg",
         apple_touch_route = "/apple-touch-icon.png",
         manifest_route    = "/site.webmanifest",
     );
 
     for host in &config.hosts {
-        html.push_str(&render_host(db, host, Some(true)));
+        html.push_str(&render_host(db, config, host, Some(true), None, &no_errors, charts, None));
     }
 
-    html.push_str(&format!(r##"<footer>Made with &#10084;&#65039; by <a href="mailto:david@connol.ly">David Connolly</a> &amp; <a href="https://claude.ai">Claude</a> &middot; <a href="https://github.com/slartibardfast/pi-glass">pi-glass v{VERSION}</a></footer>"##));
+    html.push_str(&render_footer(config));
     html.push_str("</body></html>");
     html
 }
@@ -950,3 +3254,91 @@ fn substitute_vars(s: &str, vars: &HashMap<String, String>) -> String {
     result.push_str(rest);
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(status: &str, latency: Option<f64>) -> (String, String, Option<f64>) {
+        (String::new(), status.to_string(), latency)
+    }
+
+    #[test]
+    fn sparkline_constant_latency_is_flat() {
+        let checks = vec![check("UP", Some(12.0)); 3];
+        let spark = normalize_sparkline(&checks);
+        assert_eq!(spark.pad, SPARK_BARS - 3);
+        assert_eq!(spark.bars, vec![Some(50); 3]);
+        let stats = spark.stats.unwrap();
+        assert_eq!((stats.avg, stats.stddev, stats.min, stats.max), (12.0, 0.0, 12.0, 12.0));
+    }
+
+    #[test]
+    fn sparkline_outlier_spans_full_height() {
+        // Newest first: the outlier is the latest check, so it's the last bar.
+        let checks = [check("UP", Some(100.0)), check("UP", Some(10.0)), check("UP", Some(10.0)), check("UP", Some(10.0))];
+        let spark = normalize_sparkline(&checks);
+        assert_eq!(spark.pad, SPARK_BARS - 4);
+        assert_eq!(spark.bars, vec![Some(1), Some(1), Some(1), Some(100)]);
+        let stats = spark.stats.unwrap();
+        assert_eq!((stats.avg, stats.min, stats.max), (32.5, 10.0, 100.0));
+    }
+
+    #[test]
+    fn sparkline_all_down_has_no_stats() {
+        let checks = vec![check("DOWN", None); 2];
+        let spark = normalize_sparkline(&checks);
+        assert_eq!(spark.pad, SPARK_BARS - 2);
+        assert_eq!(spark.bars, vec![None, None]);
+        assert!(spark.stats.is_none());
+    }
+
+    #[test]
+    fn sparkline_all_pending_is_not_down() {
+        let checks = vec![check(PENDING, None); 3];
+        let spark = normalize_sparkline(&checks);
+        assert_eq!(spark.bars, vec![Some(0); 3]);
+        assert!(spark.stats.is_none());
+        // Grace-period failures then a real DOWN: only the DOWN is a DOWN bar.
+        let checks = [check("DOWN", None), check(PENDING, None)];
+        assert_eq!(normalize_sparkline(&checks).bars, vec![Some(0), None]);
+    }
+
+    #[test]
+    fn sparkline_mixed_statuses() {
+        let checks = [check("UP", Some(30.0)), check("DOWN", None), check(PENDING, None), check("UP", Some(10.0))];
+        let spark = normalize_sparkline(&checks);
+        assert_eq!(spark.bars, vec![Some(1), Some(0), None, Some(100)]);
+        let stats = spark.stats.unwrap();
+        assert_eq!((stats.avg, stats.min, stats.max), (20.0, 10.0, 30.0));
+        assert!((stats.stddev - 200f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sparkline_full_history_has_no_padding() {
+        let checks = vec![check("UP", Some(5.0)); SPARK_BARS + 5];
+        assert_eq!(normalize_sparkline(&checks).pad, 0);
+    }
+
+    #[test]
+    fn debounce_short_down_run_keeps_previous() {
+        assert_eq!(debounced_state(&["DOWN", "DOWN", "UP", "UP"], "UP", 3, 2), "UP");
+    }
+
+    #[test]
+    fn debounce_down_n_downs_flip_to_down() {
+        assert_eq!(debounced_state(&["DOWN", "DOWN", "DOWN", "UP"], "UP", 3, 2), "DOWN");
+    }
+
+    #[test]
+    fn debounce_up_n_ups_recover() {
+        assert_eq!(debounced_state(&["UP", "DOWN", "DOWN", "DOWN"], "DOWN", 3, 2), "DOWN");
+        assert_eq!(debounced_state(&["UP", "UP", "DOWN", "DOWN"], "DOWN", 3, 2), "UP");
+    }
+
+    #[test]
+    fn debounce_empty_history_keeps_previous() {
+        assert_eq!(debounced_state(&[], "DOWN", 3, 2), "DOWN");
+        assert_eq!(debounced_state(&[], NO_DATA, 3, 2), NO_DATA);
+    }
+}
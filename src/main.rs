@@ -1,31 +1,124 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use std::net::IpAddr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::future::IntoFuture;
 use std::time::{Duration, Instant};
 
 use axum::body::Bytes;
-use axum::extract::State;
+use axum::extract::{ConnectInfo, Query, State};
 use chrono::Local;
+use futures_util::future::LocalBoxFuture;
+use futures_util::StreamExt;
 use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
 use surge_ping::{Client, Config as PingConfig, PingIdentifier, PingSequence};
+use tracing::{error, info, warn};
 
 use pi_glass::*;
 
-// Minimal DNS A-query for google.com
-const DNS_QUERY: [u8; 28] = [
-    0xAB, 0xCD, // ID
-    0x01, 0x00, // Flags: standard query, RD=1
-    0x00, 0x01, // QDCOUNT: 1
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // AN/NS/AR counts
-    0x06, b'g', b'o', b'o', b'g', b'l', b'e',
-    0x03, b'c', b'o', b'm',
-    0x00,       // end of name
-    0x00, 0x01, // type A
-    0x00, 0x01, // class IN
-];
+mod graphite;
+mod notify;
+
+const DNS_ID: [u8; 2] = [0xAB, 0xCD];
+
+/// How long a host given by name keeps its resolved address before a fresh lookup.
+const HOST_RESOLVE_SECS: u64 = 300;
+
+/// Seconds between retention purges — a day's cutoff doesn't need a scan every round.
+const PURGE_INTERVAL_SECS: u64 = 3600;
+
+/// Minimal DNS query: one question for `name`/`qtype`, class IN, recursion desired.
+fn dns_query(name: &str, qtype: u16) -> Vec<u8> {
+    let mut q = Vec::with_capacity(18 + name.len());
+    q.extend_from_slice(&DNS_ID);
+    q.extend_from_slice(&[0x01, 0x00]); // Flags: standard query, RD=1
+    q.extend_from_slice(&[0x00, 0x01]); // QDCOUNT: 1
+    q.extend_from_slice(&[0; 6]);       // AN/NS/AR counts
+    for label in name.trim_end_matches('.').split('.') {
+        q.push(label.len() as u8);
+        q.extend_from_slice(label.as_bytes());
+    }
+    q.push(0);                          // end of name
+    q.extend_from_slice(&qtype.to_be_bytes());
+    q.extend_from_slice(&[0x00, 0x01]); // class IN
+    q
+}
+
+/// Offset just past the (possibly compressed) name starting at `pos`; None if it
+/// runs off the end or past the 255 octets a name may take.
+fn dns_skip_name(buf: &[u8], mut pos: usize) -> Option<usize> {
+    let start = pos;
+    while pos - start < 255 {
+        let len = *buf.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            l if l & 0xC0 == 0xC0 => return Some(pos + 2), // pointer ends the name
+            l => pos += 1 + l as usize,
+        }
+    }
+    None
+}
+
+/// Check a reply to `dns_query`: our ID, a response, NOERROR, and at least one
+/// answer record of `qtype` (a CNAME chain alone doesn't count) — with `expect`, an
+/// A/AAAA answer equal to it, any of several; without, an A/AAAA answer other than
+/// 0.0.0.0 / `::` (what sinkholes hand out). A truncated (TC) reply is DOWN: the
+/// answer section can't be trusted to be complete.
+fn dns_verify(buf: &[u8], qtype: u16, expect: Option<IpAddr>) -> Result<(), String> {
+    if buf.len() < 12 || buf[..2] != DNS_ID || buf[2] & 0x80 == 0 {
+        return Err("malformed response".into());
+    }
+    match buf[3] & 0x0F {
+        0 => {}
+        2 => return Err("SERVFAIL".into()),
+        3 => return Err("NXDOMAIN".into()),
+        5 => return Err("REFUSED".into()),
+        rcode => return Err(format!("rcode {rcode}")),
+    }
+    if buf[2] & 0x02 != 0 {
+        return Err("truncated response".into());
+    }
+    let qdcount = u16::from_be_bytes([buf[4], buf[5]]);
+    let ancount = u16::from_be_bytes([buf[6], buf[7]]);
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = dns_skip_name(buf, pos).ok_or("malformed response")? + 4;
+    }
+    let mut got: Vec<IpAddr> = Vec::new();
+    let mut found = false;
+    for _ in 0..ancount {
+        pos = dns_skip_name(buf, pos).ok_or("malformed response")?;
+        let header = buf.get(pos..pos + 10).ok_or("malformed response")?;
+        let rdlen = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let rdata = buf.get(pos + 10..pos + 10 + rdlen).ok_or("malformed response")?;
+        if u16::from_be_bytes([header[0], header[1]]) == qtype {
+            let ip = match rdata.len() {
+                4  => Some(IpAddr::from(<[u8; 4]>::try_from(rdata).unwrap())),
+                16 => Some(IpAddr::from(<[u8; 16]>::try_from(rdata).unwrap())),
+                _  => None,
+            };
+            match expect {
+                Some(_) if ip == expect => return Ok(()),
+                None if !ip.is_some_and(|ip| ip.is_unspecified()) => return Ok(()),
+                _ => {}
+            }
+            found = true;
+            got.extend(ip);
+        }
+        pos += 10 + rdlen;
+    }
+    match expect {
+        Some(expect) if found => {
+            let got: Vec<String> = got.iter().map(IpAddr::to_string).collect();
+            Err(format!("wrong answer: got {}, expected {expect}", got.join(", ")))
+        }
+        None if found => Err(format!("unusable answer {}", got[0])),
+        _ => Err(format!("no {} record in answer", dns_record_name(qtype))),
+    }
+}
 
 struct PageCache {
     generation: usize,
@@ -50,15 +143,28 @@ struct AppState {
     config: Config,
     config_toml: Option<String>,
     resolved_ips: Mutex<HashMap<String, Option<String>>>,
+    check_errors: Mutex<HashMap<String, String>>,
     poll_generation: AtomicUsize,
+    /// Unix seconds at the end of the last completed poll round (0 = none yet).
+    last_poll: AtomicI64,
     page_cache: RwLock<PageCache>,
     effective_refresh_secs: AtomicUsize,
     recent_cookies: Mutex<VecDeque<String>>,
+    rate_buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+    /// Per-IP budget of credential checks that may fail; see `require_auth`.
+    auth_buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+    /// `Authorization` headers that already passed bcrypt, so a page load doesn't pay
+    /// for one verify per asset. Cleared when full.
+    auth_ok: Mutex<HashSet<String>>,
+    /// The time of each completed poll round, fanned out to `/events` listeners.
+    updates: tokio::sync::broadcast::Sender<i64>,
     css_hash: String,
     js_hash: String,
     favicon_svg_route: String,
     apple_touch_route: String,
     manifest_route: String,
+    /// When this process started (RFC 3339), for the live page's "Since start" column.
+    started_at: String,
 }
 
 async fn cors_headers(
@@ -74,6 +180,119 @@ async fn cors_headers(
     resp
 }
 
+struct TokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// Refills `ip`'s bucket at `per_minute` up to `burst` and takes a token, or returns
+/// the seconds until one is available.
+fn take_token(buckets: &Mutex<HashMap<IpAddr, TokenBucket>>, ip: IpAddr, per_minute: u32, burst: u32) -> Result<(), u64> {
+    let now = Instant::now();
+    let rate = per_minute as f64 / 60.0;
+    let burst = burst.max(1) as f64;
+    let mut buckets = buckets.lock().unwrap();
+    // Full buckets carry no state — drop them so the map can't grow without bound.
+    if buckets.len() > 256 {
+        buckets.retain(|_, b| b.tokens + now.duration_since(b.last).as_secs_f64() * rate < burst);
+    }
+    let b = buckets.entry(ip).or_insert(TokenBucket { tokens: burst, last: now });
+    b.tokens = (b.tokens + now.duration_since(b.last).as_secs_f64() * rate).min(burst);
+    b.last = now;
+    if b.tokens < 1.0 {
+        return Err(((1.0 - b.tokens) / rate).ceil() as u64);
+    }
+    b.tokens -= 1.0;
+    Ok(())
+}
+
+/// Failed logins allowed per client IP: a burst, then this many a minute.
+const AUTH_FAILURES_PER_MINUTE: u32 = 5;
+const AUTH_FAILURE_BURST: u32 = 10;
+
+/// Token-bucket limiter for routes that scan the DB. Keyed by client IP; behind a
+/// reverse proxy every client shares one bucket, which errs on the side of the Pi.
+async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    let rl = &state.config.rate_limit;
+    if rl.per_minute > 0 {
+        if let Err(retry) = take_token(&state.rate_buckets, peer.ip(), rl.per_minute, rl.burst) {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry.to_string())],
+                "rate limit exceeded",
+            ).into_response();
+        }
+    }
+    next.run(req).await
+}
+
+/// `[auth]`: 401 with a Basic challenge unless the request carries the configured
+/// credentials. A pass-through when `[auth]` is absent. Each client IP may present
+/// wrong credentials `AUTH_FAILURE_BURST` times, then `AUTH_FAILURES_PER_MINUTE`;
+/// beyond that it gets 429 without a bcrypt verify, so guessing can't pin the CPU.
+async fn require_auth(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+    use base64::Engine;
+
+    let Some(auth) = &state.config.auth else { return next.run(req).await };
+    let presented = req.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let mut ok = state.auth_ok.lock().unwrap().contains(&presented);
+    if !ok {
+        let credentials = presented.strip_prefix("Basic ")
+            .and_then(|b64| base64::engine::general_purpose::STANDARD.decode(b64.trim()).ok())
+            .and_then(|raw| String::from_utf8(raw).ok());
+        if let Some((user, pass)) = credentials.as_deref().and_then(|c| c.split_once(':')) {
+            // Charged up front, refunded below on success: only failures count.
+            if let Err(retry) = take_token(&state.auth_buckets, peer.ip(), AUTH_FAILURES_PER_MINUTE, AUTH_FAILURE_BURST) {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry.to_string())],
+                    "too many failed logins",
+                ).into_response();
+            }
+            if user == auth.username {
+                // bcrypt is deliberately slow — keep it off the async workers.
+                let (pass, hash) = (pass.to_string(), auth.password_hash.clone());
+                ok = tokio::task::spawn_blocking(move || bcrypt::verify(pass, &hash).unwrap_or(false))
+                    .await
+                    .unwrap_or(false);
+            }
+        }
+        if ok {
+            if let Some(b) = state.auth_buckets.lock().unwrap().get_mut(&peer.ip()) {
+                b.tokens = (b.tokens + 1.0).min(AUTH_FAILURE_BURST as f64);
+            }
+            let mut cache = state.auth_ok.lock().unwrap();
+            if cache.len() >= 16 {
+                cache.clear();
+            }
+            cache.insert(presented);
+        }
+    }
+    if ok {
+        return next.run(req).await;
+    }
+    let challenge = format!("Basic realm=\"{}\", charset=\"UTF-8\"", state.config.name.replace('"', ""));
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, challenge)], "authentication required").into_response()
+}
+
 async fn serve_font() -> impl axum::response::IntoResponse {
     (
         [
@@ -84,6 +303,17 @@ async fn serve_font() -> impl axum::response::IntoResponse {
     )
 }
 
+/// Whether an `Accept-Encoding` value allows `coding`: listed by name (or `*`)
+/// without `q=0`. Substring matching would take `br;q=0` as a yes.
+fn accepts_encoding(accept: &str, coding: &str) -> bool {
+    accept.split(',').any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        let name = parts.next().unwrap_or("");
+        let q = parts.find_map(|p| p.strip_prefix("q=")).and_then(|q| q.parse::<f32>().ok()).unwrap_or(1.0);
+        (name.eq_ignore_ascii_case(coding) || name == "*") && q > 0.0
+    })
+}
+
 fn encoding_response(
     content_type: &'static str,
     accept: &str,
@@ -95,10 +325,10 @@ fn encoding_response(
     use axum::response::IntoResponse;
     let cache = "public, max-age=31536000, immutable";
     let vary  = "Accept-Encoding";
-    if accept.contains("br") {
+    if accepts_encoding(accept, "br") {
         ([(CONTENT_TYPE, content_type), (CACHE_CONTROL, cache),
           (CONTENT_ENCODING, "br"), (VARY, vary)], Bytes::from_static(br)).into_response()
-    } else if accept.contains("gzip") {
+    } else if accepts_encoding(accept, "gzip") {
         ([(CONTENT_TYPE, content_type), (CACHE_CONTROL, cache),
           (CONTENT_ENCODING, "gzip"), (VARY, vary)], Bytes::from_static(gz)).into_response()
     } else {
@@ -129,18 +359,99 @@ async fn serve_favicon_ico() -> impl axum::response::IntoResponse {
       (axum::http::header::CACHE_CONTROL, "public, max-age=86400")], FAVICON_ICO)
 }
 
+async fn serve_icon(
+    axum::extract::Path(key): axum::extract::Path<String>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+    use axum::response::IntoResponse;
+    // Markup links `?v=<hash>`, so a changed icon gets a new URL.
+    let Some(icon) = find_icon(&key) else {
+        return axum::http::StatusCode::NOT_FOUND.into_response();
+    };
+    match icon.compressed {
+        Some((br, gz)) => {
+            let accept = headers.get(axum::http::header::ACCEPT_ENCODING)
+                .and_then(|v| v.to_str().ok()).unwrap_or("");
+            encoding_response(icon.content_type, accept, br, gz, icon.raw)
+        }
+        None => ([(CONTENT_TYPE, icon.content_type),
+                  (CACHE_CONTROL, "public, max-age=31536000, immutable")],
+                 Bytes::from_static(icon.raw)).into_response(),
+    }
+}
+
+/// `GET /config.toml` — the annotated starter config as a download, so a new user
+/// can go from the browser to a working file without reading the docs. Always the
+/// built-in template, never the running config (which may hold tokens).
+async fn serve_config_template() -> impl axum::response::IntoResponse {
+    ([(axum::http::header::CONTENT_TYPE, "application/toml; charset=utf-8"),
+      (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"config.toml\""),
+      (axum::http::header::CACHE_CONTROL, "no-cache")],
+     default_config_toml())
+}
+
+#[derive(Deserialize)]
+struct StatusIconParams {
+    #[serde(default)]
+    d: u32,
+}
+
+async fn serve_status_icon(Query(p): Query<StatusIconParams>) -> impl axum::response::IntoResponse {
+    // The page links `?d=<down count>`, so each distinct URL always renders the same
+    // icon — safe to cache forever; a status change swaps the URL on the next refresh.
+    ([(axum::http::header::CONTENT_TYPE, "image/svg+xml"),
+      (axum::http::header::CACHE_CONTROL, "public, max-age=31536000, immutable")],
+     render_status_icon(p.d))
+}
+
 #[tokio::main]
 async fn main() {
+    init_logging();
     #[cfg(target_os = "windows")]
     bootstrap_config_from_exe();
 
-    let (mut config, config_toml) = load_config();
+    let (mut config, config_toml) = load_config().unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    });
+    let problems = config.validate();
+    for problem in &problems {
+        warn!("Config: {problem}");
+    }
+    if !problems.is_empty() && config.is_strict() {
+        error!("Refusing to start in strict mode with {} config problem(s)", problems.len());
+        std::process::exit(1);
+    }
+    let started_at = Local::now().to_rfc3339();
+
+    // `--test-notify`: confirm tokens and URLs work before trusting them with an outage.
+    if std::env::args().any(|a| a == "--test-notify") {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build HTTP client");
+        let sent = notify::send_test(&config, &http).await;
+        if sent == 0 {
+            error!("No notification channels are configured");
+        }
+        std::process::exit(if sent == 0 { 1 } else { 0 });
+    }
 
-    // Sort services once at startup: categories in render order (tcp→ping→dns),
-    // then alphabetically within each — eliminates repeated to_lowercase sorts per render.
-    let check_order = |c: &str| match c { "tcp" => 0u8, "ping" => 1, "dns" => 2, _ => 3 };
+    // Sort services once at startup: groups in config order (ungrouped last), then
+    // categories in render order (tcp→ping→dns), then alphabetically within each —
+    // eliminates repeated to_lowercase sorts per render.
+    let mut groups: Vec<String> = Vec::new();
+    for group in config.services.iter().filter_map(|s| s.group.clone()) {
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    let group_rank = |s: &Service| s.group.as_ref().and_then(|g| groups.iter().position(|x| x == g)).unwrap_or(usize::MAX);
+    let check_order = |c: &str| match c { "tcp" => 0u8, "ping" => 1, "dns" => 2, "quorum" => 3, _ => 4 };
     config.services.sort_by(|a, b| {
-        check_order(&a.check).cmp(&check_order(&b.check))
+        group_rank(a).cmp(&group_rank(b))
+            .then_with(|| check_order(&a.check).cmp(&check_order(&b.check)))
             .then_with(|| a.label.to_lowercase().cmp(&b.label.to_lowercase()))
     });
 
@@ -149,28 +460,53 @@ async fn main() {
             .unwrap_or_else(|e| panic!("Failed to create data directory {}: {e}", parent.display()));
     }
 
-    let conn = Connection::open(&config.db_path)
+    let mut conn = Connection::open(&config.db_path)
         .unwrap_or_else(|e| panic!("Failed to open database at {}: {e}", config.db_path));
 
+    // auto_vacuum only takes on a new, still-empty file — so before journal_mode
+    // writes its header. There the hourly purge can hand freed pages back; older
+    // files keep their mode.
+    conn.execute_batch("PRAGMA auto_vacuum=INCREMENTAL;")
+        .expect("Failed to set auto_vacuum");
     if config.wal_mode {
-        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA wal_autocheckpoint=0;")
-            .expect("Failed to enable WAL mode");
+        // journal_mode answers with the mode actually in effect — not WAL when the
+        // filesystem can't do the shared-memory index. Carry on in the default mode.
+        let mode = conn.query_row("PRAGMA journal_mode=WAL", [], |row| row.get::<_, String>(0));
+        match mode {
+            Ok(mode) if mode.eq_ignore_ascii_case("wal") => {
+                conn.execute_batch("PRAGMA synchronous=NORMAL; PRAGMA wal_autocheckpoint=0;")
+                    .expect("Failed to configure WAL mode");
+                info!("Database journal mode: wal");
+            }
+            Ok(mode) => {
+                warn!("WAL mode unavailable, using journal mode {mode}");
+                config.wal_mode = false;
+            }
+            Err(e) => {
+                warn!("WAL mode unavailable ({e}), using the default journal mode");
+                config.wal_mode = false;
+            }
+        }
     }
     conn.execute_batch("PRAGMA temp_store=MEMORY; PRAGMA cache_size=-4096;")
         .expect("Failed to set temp_store");
 
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS ping_results (
-            id         INTEGER PRIMARY KEY,
-            host       TEXT NOT NULL,
-            timestamp  TEXT NOT NULL,
-            status     TEXT NOT NULL,
-            latency_ms REAL
-        );
-        CREATE INDEX IF NOT EXISTS idx_ping_host_ts ON ping_results(host, timestamp);
-        CREATE INDEX IF NOT EXISTS idx_ping_host_id ON ping_results(host, id DESC);",
-    )
-    .expect("Failed to create table");
+    migrate(&mut conn, &config).unwrap_or_else(|e| panic!("Failed to migrate database schema: {e}"));
+
+    // `--reset-history <key>`: drop one target's history and exit. A local command on
+    // purpose — the web UI never writes, so there is no HTTP equivalent.
+    if let Some(key) = arg_value("--reset-history") {
+        std::process::exit(match reset_history(conn, &key) {
+            Ok((checks, events)) => {
+                println!("Reset {key}: deleted {checks} check(s) and {events} event(s)");
+                0
+            }
+            Err(e) => {
+                error!("Failed to reset {key}: {e}");
+                1
+            }
+        });
+    }
 
     let read_conn = Connection::open_with_flags(
         &config.db_path,
@@ -178,13 +514,15 @@ async fn main() {
     )
     .unwrap_or_else(|e| panic!("Failed to open read-only database at {}: {e}", config.db_path));
     read_conn.busy_timeout(Duration::from_secs(5)).expect("Failed to set busy timeout");
-    // mmap_size: cover the full expected DB — retention × polls/day × targets × ~150 bytes/row.
+    // mmap_size: cover the full expected DB — retention × rows/day (each target at its
+    // own interval) × ~150 bytes/row.
     // Sized to config so MIPS devices with 1-2 targets get ~4MB, not 128MB.
-    let num_targets = (config.hosts.len() + config.services.len()).max(1) as u64;
-    let mmap_size = (config.retention_days as u64
-        * (86400 / config.poll_interval_secs.max(1))
-        * num_targets
-        * 150)
+    let rows_per_day = config.hosts.iter().flat_map(|h| h.targets()).cloned()
+        .chain(config.services.iter().map(|s| format!("svc:{}", s.label)))
+        .map(|key| 86400 / config.interval_for(&key))
+        .sum::<u64>()
+        .max(1);
+    let mmap_size = (config.retention_days as u64 * rows_per_day * 150)
         .max(4 * 1024 * 1024);
     read_conn.execute_batch(&format!(
         "PRAGMA temp_store=MEMORY; PRAGMA cache_size=-8192; PRAGMA mmap_size={mmap_size};"
@@ -225,15 +563,22 @@ async fn main() {
         config,
         config_toml,
         resolved_ips: Mutex::new(HashMap::new()),
+        check_errors: Mutex::new(HashMap::new()),
         poll_generation: AtomicUsize::new(0),
+        last_poll: AtomicI64::new(0),
         page_cache: RwLock::new(PageCache { generation: 0, entries: HashMap::new() }),
         effective_refresh_secs: AtomicUsize::new(effective_refresh),
         recent_cookies: Mutex::new(VecDeque::new()),
+        rate_buckets: Mutex::new(HashMap::new()),
+        auth_buckets: Mutex::new(HashMap::new()),
+        auth_ok: Mutex::new(HashSet::new()),
+        updates: tokio::sync::broadcast::channel(16).0,
         css_hash,
         js_hash,
         favicon_svg_route: favicon_svg_route.clone(),
         apple_touch_route: apple_touch_route.clone(),
         manifest_route: manifest_route.clone(),
+        started_at,
     });
 
     pre_render_startup(&state);
@@ -253,8 +598,21 @@ async fn main() {
     let css_route = format!("/static/{}.css", state.css_hash);
     let js_route = format!("/static/{}.js", state.js_hash);
 
-    let app = axum::Router::new()
-        .route("/", axum::routing::get(handler))
+    // Expensive routes (DB range scans) sit behind the per-IP rate limiter;
+    // the dashboard and static assets are served from cache and stay unlimited.
+    let api = axum::Router::new()
+        .route("/api/history", axum::routing::get(api_history))
+        .route("/api/events", axum::routing::get(api_events))
+        .route("/api/sparkline", axum::routing::get(api_sparkline))
+        .route("/export.csv", axum::routing::get(export_csv))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit));
+    // /ping authenticates with its own bearer probe_token, so it stays outside [auth].
+    let probe_route = axum::Router::new()
+        .route("/ping", axum::routing::get(probe))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit));
+
+    // Static assets: no monitoring data, so `[auth] public_assets` may leave them open.
+    let assets = axum::Router::new()
         .route(&css_route, axum::routing::get(serve_css))
         .route(&js_route, axum::routing::get(serve_js))
         .route("/font/sparks.woff2", axum::routing::get(serve_font))
@@ -264,6 +622,7 @@ async fn main() {
               (axum::http::header::CACHE_CONTROL, "public, max-age=31536000, immutable")],
              Bytes::from_static(FAVICON_SVG.as_bytes()))
         }))
+        .route("/icons/{key}", axum::routing::get(serve_icon))
         .route(&apple_touch_route, axum::routing::get(|| async {
             ([(axum::http::header::CONTENT_TYPE, "image/png"),
               (axum::http::header::CACHE_CONTROL, "public, max-age=31536000, immutable")],
@@ -286,65 +645,487 @@ async fn main() {
                   (axum::http::header::CACHE_CONTROL, "public, max-age=31536000, immutable")],
                  b.clone())
             }
-        }))
+        }));
+    let metrics_route = axum::Router::new().route("/metrics", axum::routing::get(metrics));
+
+    let mut protected = axum::Router::new()
+        .route("/", axum::routing::get(handler))
+        .route("/status.svg", axum::routing::get(serve_status_icon))
+        .route("/config.toml", axum::routing::get(serve_config_template))
+        .route("/events", axum::routing::get(live_updates))
+        .merge(api);
+    let mut public = axum::Router::new().merge(probe_route);
+    let auth = state.config.auth.as_ref();
+    if auth.is_some_and(|a| a.public_assets) { public = public.merge(assets) } else { protected = protected.merge(assets) }
+    if auth.is_some_and(|a| a.public_metrics) { public = public.merge(metrics_route) } else { protected = protected.merge(metrics_route) }
+    let app = protected
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), require_auth))
+        .merge(public)
         .layer(axum::middleware::from_fn(cors_headers))
         .with_state(state.clone());
 
+    // Load the certificate before binding: a bad path or mismatched key is a startup
+    // error, never a silent fallback to plain HTTP.
+    let tls = tls_acceptor(&state.config).unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    });
     let listener = tokio::net::TcpListener::bind(&state.config.listen)
         .await
         .unwrap_or_else(|e| panic!("Failed to bind {}: {e}", state.config.listen));
 
-    eprintln!("Listening on {}", state.config.listen);
-    axum::serve(listener, app).await.unwrap();
+    info!("Listening on {}://{}", if tls.is_some() { "https" } else { "http" }, state.config.listen);
+    sd_notify("READY=1");
+
+    let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down");
+        sd_notify("STOPPING=1");
+        let _ = stop_tx.send(true);
+    });
+    let stopped = |mut rx: tokio::sync::watch::Receiver<bool>| async move {
+        let _ = rx.wait_for(|stop| *stop).await;
+    };
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let server: futures_util::future::BoxFuture<'static, std::io::Result<()>> = match tls {
+        // TapIo passes the listener's SocketAddr through as ConnectInfo (rate_limit needs it).
+        Some(acceptor) => Box::pin(axum::serve(axum::serve::ListenerExt::tap_io(TlsListener::spawn(listener, acceptor), |tls| {
+            let _ = tls.get_ref().0.set_nodelay(true);
+        }), app).with_graceful_shutdown(stopped(stop_rx.clone())).into_future()),
+        None => Box::pin(axum::serve(listener, app).with_graceful_shutdown(stopped(stop_rx.clone())).into_future()),
+    };
+    // Graceful shutdown waits for open connections; a lingering keep-alive gets two
+    // seconds, not the systemd stop timeout.
+    tokio::select! {
+        res = server => res.unwrap(),
+        _ = async { stopped(stop_rx).await; tokio::time::sleep(Duration::from_secs(2)).await } => {}
+    }
+    close_database(&state);
+}
+
+/// Schema steps, in order. `PRAGMA user_version` records how many have been applied,
+/// so each runs once per database. Append only: never edit a step that has shipped.
+const MIGRATIONS: &[&str] = &[
+    // 1: baseline — everything up to the `kind` column.
+    "CREATE TABLE IF NOT EXISTS ping_results (
+        id          INTEGER PRIMARY KEY,
+        host        TEXT NOT NULL,
+        timestamp   TEXT NOT NULL,
+        status      TEXT NOT NULL,
+        latency_ms  REAL,
+        latency_us  INTEGER,
+        cert_expiry INTEGER,
+        kind        TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_ping_host_ts ON ping_results(host, timestamp);
+    CREATE INDEX IF NOT EXISTS idx_ping_host_id ON ping_results(host, id DESC);
+    CREATE TABLE IF NOT EXISTS events (
+        id          INTEGER PRIMARY KEY,
+        host        TEXT NOT NULL,
+        timestamp   TEXT NOT NULL,
+        status      TEXT NOT NULL,
+        previous    TEXT NOT NULL,
+        lasted_secs INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_events_ts ON events(timestamp);",
+];
+
+/// Brings the database up to `MIGRATIONS.len()`, one transaction per step.
+fn migrate(conn: &mut Connection, config: &Config) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        warn!("Database schema version {version} is newer than this build ({}); carrying on", MIGRATIONS.len());
+        return Ok(());
+    }
+    // Databases from before the runner are at version 0 but may hold a table whose
+    // columns were added ad hoc; fill in what's missing so the baseline fits.
+    let legacy = version == 0 && conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'ping_results'")?
+        .exists([])?;
+    if legacy {
+        for (column, ty) in [("latency_us", "INTEGER"), ("cert_expiry", "INTEGER"), ("kind", "TEXT")] {
+            let exists = conn
+                .prepare("SELECT 1 FROM pragma_table_info('ping_results') WHERE name = ?1")?
+                .exists([column])?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE ping_results ADD COLUMN {column} {ty}"))?;
+                if column == "kind" {
+                    backfill_kind(conn, config)?;
+                }
+            }
+        }
+    }
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(step)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        info!("Database schema migrated to version {}", i + 1);
+    }
+    Ok(())
+}
+
+/// Labels the rows written before `ping_results.kind` existed, from the current
+/// config. Runs once, as the column is added; keys that match nothing stay NULL.
+fn backfill_kind(conn: &mut Connection, config: &Config) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute("UPDATE ping_results SET kind = 'host' WHERE host NOT LIKE 'svc:%'", [])?;
+    {
+        let mut update = tx.prepare("UPDATE ping_results SET kind = ?1 WHERE host = ?2")?;
+        for svc in &config.services {
+            update.execute(params![svc.check, format!("svc:{}", svc.label)])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM (`systemctl stop`) on Unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => { sigterm.recv().await; }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Waits out an in-flight poll round write, folds the WAL back into the database
+/// and closes both connections (which removes the -wal/-shm files), then exits
+/// while still holding the write lock so the poll thread can't start another write.
+fn close_database(state: &AppState) -> ! {
+    let mut db = state.db.lock().unwrap();
+    if state.config.wal_mode {
+        if let Err(e) = db.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);") {
+            error!("Failed to checkpoint WAL: {e}");
+        }
+    }
+    // The writer closes last: the last connection out deletes the WAL.
+    let mut read_db = state.read_db.lock().unwrap();
+    for conn in [&mut *read_db, &mut *db] {
+        let conn = std::mem::replace(conn, Connection::open_in_memory().expect("Failed to open in-memory database"));
+        if let Err((_, e)) = conn.close() {
+            error!("Failed to close database: {e}");
+        }
+    }
+    std::process::exit(0);
+}
+
+// --- HTTPS ---
+
+/// `Some` when `tls_cert` and `tls_key` are set; an error names the file at fault.
+fn tls_acceptor(config: &Config) -> Result<Option<tokio_rustls::TlsAcceptor>, String> {
+    use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+    let (cert_path, key_path) = match (&config.tls_cert, &config.tls_key) {
+        (None, None) => return Ok(None),
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Err("tls_cert and tls_key must be set together".to_string()),
+    };
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read tls_cert {cert_path}: {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("tls_cert {cert_path} contains no certificates"));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("Failed to read tls_key {key_path}: {e}"))?;
+    let server = tokio_rustls::rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Can't use tls_cert {cert_path} with tls_key {key_path}: {e}"))?;
+    Ok(Some(tokio_rustls::TlsAcceptor::from(Arc::new(server))))
+}
+
+type TlsConn = (tokio_rustls::server::TlsStream<tokio::net::TcpStream>, SocketAddr);
+
+/// Hands axum connections that have finished their TLS handshake. Each handshake
+/// runs in its own task (10 s limit), so a stalled client can't hold up the others.
+struct TlsListener {
+    ready: tokio::sync::mpsc::Receiver<TlsConn>,
+    local: SocketAddr,
+}
+
+impl TlsListener {
+    fn spawn(listener: tokio::net::TcpListener, acceptor: tokio_rustls::TlsAcceptor) -> Self {
+        let local = listener.local_addr().expect("bound listener has an address");
+        let (tx, ready) = tokio::sync::mpsc::channel(64);
+        tokio::spawn(async move {
+            loop {
+                let (tcp, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        // Usually EMFILE; back off rather than spin.
+                        warn!("Accept failed: {e}");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                let (acceptor, tx) = (acceptor.clone(), tx.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(Duration::from_secs(10), acceptor.accept(tcp)).await {
+                        Ok(Ok(tls)) => { let _ = tx.send((tls, peer)).await; }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {peer} failed: {e}"),
+                        Err(_) => tracing::debug!("TLS handshake with {peer} timed out"),
+                    }
+                });
+            }
+        });
+        Self { ready, local }
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = tokio_rustls::server::TlsStream<tokio::net::TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> TlsConn {
+        match self.ready.recv().await {
+            Some(conn) => conn,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        Ok(self.local)
+    }
+}
+
+// --- systemd notification ---
+
+/// Send a state line (`READY=1`, `WATCHDOG=1`) to systemd's notify socket.
+/// No-op unless NOTIFY_SOCKET is set (i.e. not running under `Type=notify`);
+/// best effort — a failed send must never affect monitoring.
+#[cfg(unix)]
+fn sd_notify(msg: &str) {
+    use std::os::unix::net::UnixDatagram;
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else { return };
+    let Ok(sock) = UnixDatagram::unbound() else { return };
+    // A leading '@' names a Linux abstract-namespace socket.
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.as_encoded_bytes().strip_prefix(b"@") {
+        use std::os::linux::net::SocketAddrExt;
+        if let Ok(addr) = std::os::unix::net::SocketAddr::from_abstract_name(name) {
+            let _ = sock.send_to_addr(msg.as_bytes(), &addr);
+        }
+        return;
+    }
+    let _ = sock.send_to(msg.as_bytes(), &path);
 }
 
+#[cfg(not(unix))]
+fn sd_notify(_msg: &str) {}
+
 // --- Service check functions ---
 
-async fn check_ping(client: &Client, target: &str, seq: u16, timeout_secs: u64) -> (bool, Option<f64>, Option<String>) {
-    let addr: IpAddr = match tokio::net::lookup_host(format!("{target}:0")).await {
-        Ok(mut addrs) => match addrs.next() {
-            Some(sa) => sa.ip(),
-            None => return (false, None, None),
+/// Outcome of one check. `error` is a short reason shown in the detail view when
+/// the check fails ("timeout", "connection refused", "DNS lookup failed").
+struct CheckResult {
+    up: bool,
+    latency_ms: Option<f64>,
+    resolved_ip: Option<String>,
+    error: Option<String>,
+    /// `tls` checks: the leaf certificate's notAfter, unix seconds.
+    cert_expiry: Option<i64>,
+}
+
+impl CheckResult {
+    fn up(latency_ms: f64, resolved_ip: Option<String>) -> Self {
+        Self { up: true, latency_ms: Some(latency_ms), resolved_ip, error: None, cert_expiry: None }
+    }
+
+    fn down(error: impl Into<String>, resolved_ip: Option<String>) -> Self {
+        Self { up: false, latency_ms: None, resolved_ip, error: Some(error.into()), cert_expiry: None }
+    }
+}
+
+/// Short, stable wording for the socket errors a check commonly hits.
+fn io_reason(e: &std::io::Error) -> String {
+    use std::io::ErrorKind::*;
+    match e.kind() {
+        ConnectionRefused  => "connection refused".to_string(),
+        ConnectionReset    => "connection reset".to_string(),
+        HostUnreachable    => "host unreachable".to_string(),
+        NetworkUnreachable => "network unreachable".to_string(),
+        TimedOut           => "timeout".to_string(),
+        _                  => e.to_string(),
+    }
+}
+
+fn ping_reason(e: &surge_ping::SurgeError) -> String {
+    match e {
+        surge_ping::SurgeError::Timeout { .. } => "timeout".to_string(),
+        e => e.to_string(),
+    }
+}
+
+/// ICMP matching model: surge_ping hands an echo reply to the pinger waiting on the
+/// same (destination address, identifier, sequence). Every ICMP target — host, group
+/// member or `check = "ping"` service — therefore gets its own identifier for the life
+/// of the process and its own sequence counter, advanced on every probe. Two targets
+/// resolving to the same address can't steal each other's replies, and a late reply to
+/// a timed-out probe carries an old sequence that no pinger is waiting for.
+/// Don't go back to one shared identifier or one per-round sequence.
+struct IcmpIds {
+    next_ident: u16,
+    targets: HashMap<String, (u16, u16)>,
+}
+
+impl IcmpIds {
+    fn new() -> Self {
+        // Seed from the pid so a second pi-glass (or a manual `ping`) on the same box
+        // is unlikely to start in the same identifier range.
+        Self { next_ident: std::process::id() as u16, targets: HashMap::new() }
+    }
+
+    /// Identifier and next sequence number for `target`.
+    fn next(&mut self, target: &str) -> (PingIdentifier, PingSequence) {
+        let next_ident = &mut self.next_ident;
+        let (ident, seq) = self.targets.entry(target.to_string()).or_insert_with(|| {
+            let ident = *next_ident;
+            *next_ident = next_ident.wrapping_add(1);
+            (ident, 0)
+        });
+        let out = (PingIdentifier(*ident), PingSequence(*seq));
+        *seq = seq.wrapping_add(1);
+        out
+    }
+}
+
+/// ICMP client bound to `source` / `interface` when given. The socket family follows
+/// the source address; unbound clients use the default (IPv4) config.
+fn icmp_client(source: Option<&str>, interface: Option<&str>) -> Result<Client, String> {
+    let mut builder = PingConfig::builder();
+    if let Some(src) = source {
+        let ip: IpAddr = src.parse().map_err(|_| format!("source '{src}' is not an IP address"))?;
+        builder = builder.bind(SocketAddr::new(ip, 0));
+        if ip.is_ipv6() {
+            builder = builder.kind(surge_ping::ICMP::V6);
+        }
+    }
+    if let Some(iface) = interface {
+        builder = builder.interface(iface);
+    }
+    Client::new(&builder.build()).map_err(|e| format!("ICMP client unavailable: {e}"))
+}
+
+/// ICMP echo to a LAN host. A literal address is pinged as-is; a hostname uses
+/// `cached` or is resolved (in the client's `family`) before the timer starts, and
+/// the address used is reported as `resolved_ip`.
+async fn check_host(client: &Client, target: &str, cached: Option<IpAddr>, family: &str, ident: PingIdentifier, seq: PingSequence, timeout_secs: u64) -> CheckResult {
+    let addr = match target.parse::<IpAddr>().ok().or(cached) {
+        Some(ip) => ip,
+        None => match resolve(&format!("{target}:0"), Some(family)).await {
+            Ok(sa) => sa.ip(),
+            Err(e) => return CheckResult::down(e, None),
         },
-        Err(_) => return (false, None, None),
     };
+    let resolved_ip = target.parse::<IpAddr>().is_err().then(|| addr.to_string());
+    let mut pinger = client.pinger(addr, ident).await;
+    pinger.timeout(Duration::from_secs(timeout_secs));
+    let payload = [0u8; 56];
+    match pinger.ping(seq, &payload).await {
+        Ok((_pkt, dur)) => CheckResult::up(dur.as_secs_f64() * 1000.0, resolved_ip),
+        Err(e) => CheckResult::down(ping_reason(&e), resolved_ip),
+    }
+}
 
-    let mut pinger = client.pinger(addr, PingIdentifier(0xAB)).await;
+/// ICMPv6 client for `family = "v6"` ping services (the route clients are ICMPv4
+/// unless their source address is v6).
+fn icmp6_client() -> Client {
+    Client::new(&PingConfig::builder().kind(surge_ping::ICMP::V6).build())
+        .expect("Failed to create ICMPv6 ping client (need CAP_NET_RAW)")
+}
+
+/// First address `host_port` resolves to in `family` ("v4"/"v6"; unset: the first of any).
+async fn resolve(host_port: &str, family: Option<&str>) -> Result<SocketAddr, &'static str> {
+    let addrs = tokio::net::lookup_host(host_port).await.map_err(|_| "DNS lookup failed")?;
+    let mut addrs = addrs.filter(|a| match family {
+        Some("v4") => a.is_ipv4(),
+        Some("v6") => a.is_ipv6(),
+        _ => true,
+    });
+    addrs.next().ok_or(match family {
+        Some("v4") => "no IPv4 address",
+        Some("v6") => "no IPv6 address",
+        _ => "DNS lookup returned no address",
+    })
+}
+
+async fn check_ping(client: &Client, target: &str, family: Option<&str>, ident: PingIdentifier, seq: PingSequence, timeout_secs: u64) -> CheckResult {
+    let addr: IpAddr = match resolve(&format!("{target}:0"), family).await {
+        Ok(sa) => sa.ip(),
+        Err(e) => return CheckResult::down(e, None),
+    };
+
+    let mut pinger = client.pinger(addr, ident).await;
     pinger.timeout(Duration::from_secs(timeout_secs));
 
     let payload = [0u8; 56];
-    match pinger.ping(PingSequence(seq), &payload).await {
-        Ok((_packet, duration)) => (true, Some(duration.as_secs_f64() * 1000.0), Some(addr.to_string())),
-        Err(_) => (false, None, Some(addr.to_string())),
+    match pinger.ping(seq, &payload).await {
+        Ok((_packet, duration)) => CheckResult::up(duration.as_secs_f64() * 1000.0, Some(addr.to_string())),
+        Err(e) => CheckResult::down(ping_reason(&e), Some(addr.to_string())),
     }
 }
 
-async fn check_dns(nameserver: &str, timeout_secs: u64) -> (bool, Option<f64>, Option<String>) {
+async fn check_dns(nameserver: &str, name: &str, qtype: u16, expect: Option<IpAddr>, timeout_secs: u64) -> CheckResult {
     let addr = format!("{nameserver}:53");
     let bind_addr = if nameserver.contains(':') { "[::]:0" } else { "0.0.0.0:0" };
     let sock = match tokio::net::UdpSocket::bind(bind_addr).await {
         Ok(s) => s,
-        Err(_) => return (false, None, None),
+        Err(e) => return CheckResult::down(io_reason(&e), None),
     };
 
-    if sock.connect(&addr).await.is_err() {
-        return (false, None, None);
+    if let Err(e) = sock.connect(&addr).await {
+        return CheckResult::down(io_reason(&e), None);
     }
 
-    if sock.send(&DNS_QUERY).await.is_err() {
-        return (false, None, None);
+    if let Err(e) = sock.send(&dns_query(name, qtype)).await {
+        return CheckResult::down(io_reason(&e), None);
     }
     let start = Instant::now();
 
     let mut buf = [0u8; 512];
     // nameserver IS the IP — no resolution to show
     match tokio::time::timeout(Duration::from_secs(timeout_secs), sock.recv(&mut buf)).await {
-        Ok(Ok(n)) if n > 0 => (true, Some(start.elapsed().as_secs_f64() * 1000.0), None),
-        _ => (false, None, None),
+        Ok(Ok(n)) if n > 0 => {
+            let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+            match dns_verify(&buf[..n], qtype, expect) {
+                Ok(()) => CheckResult::up(latency_ms, None),
+                Err(e) => CheckResult::down(e, None),
+            }
+        }
+        Ok(Ok(_)) => CheckResult::down("empty response", None),
+        Ok(Err(e)) => CheckResult::down(io_reason(&e), None),
+        Err(_) => CheckResult::down("timeout", None),
     }
 }
 
-async fn check_tcp(target: &str, timeout_secs: u64) -> (bool, Option<f64>, Option<String>) {
+async fn check_tcp(target: &str, family: Option<&str>, timeout_secs: u64) -> CheckResult {
+    // Pinned to a family: resolve first (outside the timer), then connect to that address.
+    if family.is_some() {
+        let addr = match resolve(target, family).await {
+            Ok(addr) => addr,
+            Err(e) => return CheckResult::down(e, None),
+        };
+        let start = Instant::now();
+        return match tokio::time::timeout(Duration::from_secs(timeout_secs), tokio::net::TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => CheckResult::up(start.elapsed().as_secs_f64() * 1000.0, Some(addr.ip().to_string())),
+            Ok(Err(e)) => CheckResult::down(io_reason(&e), Some(addr.ip().to_string())),
+            Err(_) => CheckResult::down("timeout", Some(addr.ip().to_string())),
+        };
+    }
     let start = Instant::now();
     match tokio::time::timeout(
         Duration::from_secs(timeout_secs),
@@ -354,63 +1135,655 @@ async fn check_tcp(target: &str, timeout_secs: u64) -> (bool, Option<f64>, Optio
     {
         Ok(Ok(stream)) => {
             let peer_ip = stream.peer_addr().ok().map(|a| a.ip().to_string());
-            (true, Some(start.elapsed().as_secs_f64() * 1000.0), peer_ip)
+            CheckResult::up(start.elapsed().as_secs_f64() * 1000.0, peer_ip)
+        }
+        Ok(Err(e)) => CheckResult::down(io_reason(&e), None),
+        Err(_) => CheckResult::down("timeout", None),
+    }
+}
+
+/// GET `url` with `svc`'s User-Agent and headers, following up to `max_redirects`;
+/// UP if the final status is within `expected_status` (and the body holds
+/// `body_contains`, when set). The host is resolved first and pinned into the
+/// client, so the timer covers connect, TLS and time to first byte — not the
+/// lookup (redirects to another host are the exception) nor the body read.
+async fn check_http(url: &str, svc: &Service, timeout_secs: u64) -> CheckResult {
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(u) => u,
+        Err(_) => return CheckResult::down("invalid URL", None),
+    };
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return CheckResult::down("URL has no host", None);
+    };
+    let addr = match resolve(&format!("{host}:{port}"), None).await {
+        Ok(addr) => addr,
+        Err(e) => return CheckResult::down(e, None),
+    };
+    let user_agent = svc.user_agent.clone().unwrap_or_else(|| format!("pi-glass/{VERSION}"));
+    let max_redirects = svc.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS);
+    let redirect = match max_redirects {
+        0 => reqwest::redirect::Policy::none(),
+        n => reqwest::redirect::Policy::limited(n),
+    };
+    let mut builder = reqwest::Client::builder()
+        .redirect(redirect)
+        .user_agent(user_agent)
+        .timeout(Duration::from_secs(timeout_secs));
+    if let Some(domain) = parsed.domain() {
+        builder = builder.resolve(domain, addr);
+    }
+    let client = match builder.build() {
+        Ok(c) => c,
+        Err(_) => return CheckResult::down("HTTP client setup failed", None),
+    };
+    let ip = Some(addr.ip().to_string());
+    let mut request = client.get(parsed);
+    for (name, value) in &svc.headers {
+        // Config::validate reports bad headers by name; never echo the value.
+        let parsed = (reqwest::header::HeaderName::from_bytes(name.as_bytes()), reqwest::header::HeaderValue::from_str(value));
+        let (Ok(name), Ok(value)) = parsed else {
+            return CheckResult::down(format!("invalid header {name}"), None);
+        };
+        request = request.header(name, value);
+    }
+
+    let start = Instant::now();
+    match request.send().await {
+        Ok(resp) => {
+            let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+            let status = resp.status().as_u16();
+            let (lo, hi) = svc.expected_status();
+            if !(lo..=hi).contains(&status) {
+                return CheckResult::down(format!("HTTP {status}"), ip);
+            }
+            let cap = svc.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+            match &svc.body_contains {
+                Some(needle) if !body_contains(resp, needle.as_bytes(), cap).await => {
+                    CheckResult::down("body mismatch", ip)
+                }
+                _ => CheckResult::up(latency_ms, ip),
+            }
+        }
+        Err(e) if e.is_timeout() => CheckResult::down("timeout", ip),
+        Err(e) if e.is_redirect() => CheckResult::down(format!("too many redirects (>{max_redirects})"), ip),
+        Err(e) if e.is_connect() => CheckResult::down("connection failed", ip),
+        Err(_) => CheckResult::down("request failed", ip),
+    }
+}
+
+/// Reads `resp` until `needle` turns up, the body ends or `cap` bytes have been read
+/// — never the whole body. Only the new bytes (plus a needle's overlap) are searched
+/// per chunk.
+async fn body_contains(mut resp: reqwest::Response, needle: &[u8], cap: usize) -> bool {
+    if needle.is_empty() {
+        return true;
+    }
+    let mut buf = Vec::new();
+    while buf.len() < cap {
+        let Ok(Some(chunk)) = resp.chunk().await else { break };
+        let from = buf.len().saturating_sub(needle.len() - 1);
+        buf.extend_from_slice(&chunk[..chunk.len().min(cap - buf.len())]);
+        if buf[from..].windows(needle.len()).any(|w| w == needle) {
+            return true;
         }
-        _ => (false, None, None),
+    }
+    false
+}
+
+/// One DER TLV at the start of `buf`: (tag, contents, rest).
+fn der_tlv(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, buf) = buf.split_first()?;
+    let (&len0, mut buf) = buf.split_first()?;
+    let len = if len0 < 0x80 {
+        len0 as usize
+    } else {
+        let n = (len0 & 0x7f) as usize;
+        if n == 0 || n > 4 || buf.len() < n {
+            return None;
+        }
+        let len = buf[..n].iter().fold(0usize, |acc, &b| acc << 8 | b as usize);
+        buf = &buf[n..];
+        len
+    };
+    (buf.len() >= len).then(|| (tag, &buf[..len], &buf[len..]))
+}
+
+/// notAfter of a DER X.509 certificate as unix seconds — just enough of a walk
+/// through tbsCertificate to reach `validity`, no general ASN.1 parsing.
+fn cert_not_after(der: &[u8]) -> Option<i64> {
+    let (_, cert, _) = der_tlv(der)?;
+    let (_, tbs, _) = der_tlv(cert)?;
+    let (tag, _, after_version) = der_tlv(tbs)?;
+    let rest = if tag == 0xA0 { after_version } else { tbs };  // [0] version is optional
+    let (_, _, rest) = der_tlv(rest)?;  // serialNumber
+    let (_, _, rest) = der_tlv(rest)?;  // signature
+    let (_, _, rest) = der_tlv(rest)?;  // issuer
+    let (_, validity, _) = der_tlv(rest)?;
+    let (_, _, rest) = der_tlv(validity)?;  // notBefore
+    let (tag, time, _) = der_tlv(rest)?;
+    let time = std::str::from_utf8(time).ok()?;
+    let format = match tag {
+        0x17 => "%y%m%d%H%M%SZ",    // UTCTime
+        0x18 => "%Y%m%d%H%M%SZ",    // GeneralizedTime
+        _ => return None,
+    };
+    chrono::NaiveDateTime::parse_from_str(time, format).ok().map(|t| t.and_utc().timestamp())
+}
+
+/// Accepts any certificate chain (`allow_self_signed`) while still checking the
+/// handshake signatures, so the connection is real even if the issuer isn't trusted.
+#[derive(Debug)]
+struct AnyCert(Arc<tokio_rustls::rustls::crypto::CryptoProvider>);
+
+impl tokio_rustls::rustls::client::danger::ServerCertVerifier for AnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls_pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls_pki_types::CertificateDer<'_>],
+        _server_name: &rustls_pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls_pki_types::UnixTime,
+    ) -> Result<tokio_rustls::rustls::client::danger::ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(tokio_rustls::rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &tokio_rustls::rustls::DigitallySignedStruct,
+    ) -> Result<tokio_rustls::rustls::client::danger::HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        tokio_rustls::rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &tokio_rustls::rustls::DigitallySignedStruct,
+    ) -> Result<tokio_rustls::rustls::client::danger::HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        tokio_rustls::rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<tokio_rustls::rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Client configs for `tls` checks, built once: public roots, or accept-anything.
+fn tls_config(allow_self_signed: bool) -> Arc<tokio_rustls::rustls::ClientConfig> {
+    use std::sync::OnceLock;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+    static VERIFIED: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    static ANY: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    if allow_self_signed {
+        ANY.get_or_init(|| {
+            let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+            Arc::new(ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AnyCert(provider)))
+                .with_no_client_auth())
+        }).clone()
+    } else {
+        VERIFIED.get_or_init(|| {
+            let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+            Arc::new(ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+        }).clone()
+    }
+}
+
+/// TLS handshake with `host:port`, SNI from the host part. Latency is connect plus
+/// handshake (resolution excluded). UP while the leaf certificate is trusted (or
+/// `allow_self_signed`) and more than `warn_days` from expiry.
+async fn check_tls(target: &str, allow_self_signed: bool, warn_days: i64, timeout_secs: u64) -> CheckResult {
+    let host = target.rsplit_once(':').map_or(target, |(h, _)| h).trim_matches(['[', ']']);
+    let server_name = match rustls_pki_types::ServerName::try_from(host.to_string()) {
+        Ok(name) => name,
+        Err(_) => return CheckResult::down("invalid server name", None),
+    };
+    let addr = match resolve(target, None).await {
+        Ok(addr) => addr,
+        Err(e) => return CheckResult::down(e, None),
+    };
+    let ip = Some(addr.ip().to_string());
+    let connector = tokio_rustls::TlsConnector::from(tls_config(allow_self_signed));
+
+    let start = Instant::now();
+    let handshake = async {
+        let tcp = tokio::net::TcpStream::connect(addr).await?;
+        connector.connect(server_name, tcp).await
+    };
+    let stream = match tokio::time::timeout(Duration::from_secs(timeout_secs), handshake).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            // rustls reports certificate problems as InvalidData wrapping its own error
+            let reason = match e.get_ref() {
+                Some(inner) if e.kind() == std::io::ErrorKind::InvalidData => inner.to_string(),
+                _ => io_reason(&e),
+            };
+            return CheckResult::down(reason, ip);
+        }
+        Err(_) => return CheckResult::down("timeout", ip),
+    };
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let leaf = stream.get_ref().1.peer_certificates().and_then(|certs| certs.first());
+    let Some(not_after) = leaf.and_then(|cert| cert_not_after(cert)) else {
+        return CheckResult::down("unreadable certificate", ip);
+    };
+    let days_left = (not_after - Local::now().timestamp()).div_euclid(86400);
+    let expires = chrono::DateTime::from_timestamp(not_after, 0).map_or_else(String::new, |t| t.format("%Y-%m-%d").to_string());
+    let mut result = if days_left < 0 {
+        CheckResult::down(format!("certificate expired {expires}"), ip)
+    } else if days_left < warn_days {
+        CheckResult::down(format!("certificate expires in {days_left}d ({expires})"), ip)
+    } else {
+        CheckResult::up(latency_ms, ip)
+    };
+    result.cert_expiry = Some(not_after);
+    result
+}
+
+/// A service with several targets: tries them in order and is UP on the first that
+/// answers, with that check's latency and `resolved_ip` naming the target (plus the
+/// address it reached). DOWN only when all fail; the error lists each reason.
+async fn check_failover(targets: &[String], attempts: Vec<LocalBoxFuture<'_, CheckResult>>) -> CheckResult {
+    let mut reasons = Vec::new();
+    for (target, attempt) in targets.iter().zip(attempts) {
+        let mut result = attempt.await;
+        if result.up {
+            result.resolved_ip = Some(match result.resolved_ip {
+                Some(ip) if ip != *target => format!("{target} ({ip})"),
+                _ => target.clone(),
+            });
+            return result;
+        }
+        reasons.push(format!("{target}: {}", result.error.as_deref().unwrap_or("DOWN")));
+    }
+    CheckResult::down(reasons.join("; "), None)
+}
+
+/// Runs `argv` directly — no shell, so nothing in it is interpreted — and times it
+/// from spawn to exit. Exit 0 is UP; otherwise the last stderr line is the reason.
+/// The child leads its own process group, and on timeout the whole group is killed,
+/// so a wrapper script can't leave its children running.
+async fn check_exec(argv: &[String], timeout_secs: u64) -> CheckResult {
+    let Some((program, args)) = argv.split_first() else {
+        return CheckResult::down("empty command", None);
+    };
+    let start = Instant::now();
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => return CheckResult::down(io_reason(&e), None),
+    };
+    let pid = child.id();
+    let stderr = child.stderr.take();
+    let run = async {
+        let reason = match stderr {
+            Some(stderr) => last_line(stderr, 800).await,
+            None => String::new(),
+        };
+        child.wait().await.map(|status| (status, reason))
+    };
+    match tokio::time::timeout(Duration::from_secs(timeout_secs), run).await {
+        Ok(Ok((status, _))) if status.success() => CheckResult::up(start.elapsed().as_secs_f64() * 1000.0, None),
+        Ok(Ok((status, reason))) if reason.is_empty() => CheckResult::down(match status.code() {
+            Some(code) => format!("exit status {code}"),
+            None => "killed by signal".to_string(),
+        }, None),
+        Ok(Ok((_, reason))) => CheckResult::down(reason.chars().take(200).collect::<String>(), None),
+        Ok(Err(e)) => CheckResult::down(io_reason(&e), None),
+        Err(_) => {
+            kill_process_group(pid);
+            CheckResult::down("timeout", None)
+        }
+    }
+}
+
+/// Reads `r` to EOF keeping only its last non-blank line, at most `max` bytes of it —
+/// a chatty command can't grow the buffer.
+async fn last_line(mut r: impl tokio::io::AsyncRead + Unpin, max: usize) -> String {
+    use tokio::io::AsyncReadExt;
+    let (mut line, mut last) = (Vec::new(), Vec::new());
+    let mut buf = [0u8; 4096];
+    while let Ok(n @ 1..) = r.read(&mut buf).await {
+        for &b in &buf[..n] {
+            match b {
+                b'\n' if line.trim_ascii().is_empty() => line.clear(),
+                b'\n' => last = std::mem::take(&mut line),
+                _ if line.len() < max => line.push(b),
+                _ => {}
+            }
+        }
+    }
+    if !line.trim_ascii().is_empty() {
+        last = line;
+    }
+    String::from_utf8_lossy(last.trim_ascii()).into_owned()
+}
+
+/// SIGKILL the process group `pid` leads (see check_exec). Elsewhere kill_on_drop
+/// only reaches the direct child.
+#[cfg(unix)]
+fn kill_process_group(pid: Option<u32>) {
+    if let Some(pid) = pid.and_then(|p| i32::try_from(p).ok()) {
+        // SAFETY: kill(2) takes no pointers; a negative pid names the process group.
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: Option<u32>) {}
+
+// --- Check log ---
+
+#[derive(Serialize)]
+struct CheckLogLine<'a> {
+    ts: &'a str,
+    key: &'a str,
+    status: &'a str,
+    latency_ms: Option<f64>,
+    error: Option<&'a str>,
+}
+
+/// Append one JSON line per row to `path`, first rotating it to `<path>.1` (replacing
+/// any previous one) if it has grown past `max_bytes`. Bounded at ~2× `max_bytes` on
+/// disk. Errors are logged and dropped — the audit trail must never stop polling.
+fn append_check_log(path: &str, max_bytes: u64, rows: &[(String, String, &'static str, Option<f64>)], errors: &HashMap<String, String>) {
+    use std::io::Write as _;
+
+    if std::fs::metadata(path).is_ok_and(|m| m.len() > max_bytes) {
+        if let Err(e) = std::fs::rename(path, format!("{path}.1")) {
+            warn!("check_log: rotate {path} failed: {e}");
+        }
+    }
+    let mut buf = Vec::new();
+    for (key, ts, status, latency_ms) in rows {
+        let line = CheckLogLine { ts, key, status, latency_ms: *latency_ms, error: errors.get(key).map(String::as_str) };
+        serde_json::to_writer(&mut buf, &line).unwrap();
+        buf.push(b'\n');
+    }
+    let res = std::fs::OpenOptions::new().create(true).append(true).open(path)
+        .and_then(|mut f| f.write_all(&buf));
+    if let Err(e) = res {
+        warn!("check_log: write {path} failed: {e}");
     }
 }
 
 // --- Poll loop ---
 
 async fn poll_loop(state: Arc<AppState>) {
-    let client = Client::new(&PingConfig::default())
-        .expect("Failed to create ping client (need CAP_NET_RAW)");
+    // One client per distinct (source, interface) — each is its own raw socket. The
+    // default route must work; a host route that can't be opened (interface gone,
+    // address not local) leaves just its hosts DOWN with the reason.
+    let default_route = (state.config.icmp_source.as_deref(), state.config.icmp_interface.as_deref());
+    let default_client = icmp_client(default_route.0, default_route.1)
+        .unwrap_or_else(|e| panic!("Failed to create ping client (need CAP_NET_RAW): {e}"));
+    let mut clients = HashMap::new();
+    for route in state.config.hosts.iter().map(|h| h.icmp_route(&state.config)) {
+        if route != default_route && !clients.contains_key(&route) {
+            let client = icmp_client(route.0, route.1);
+            if let Err(e) = &client {
+                error!("ICMP source {} interface {}: {e}", route.0.unwrap_or("-"), route.1.unwrap_or("-"));
+            }
+            clients.insert(route, client);
+        }
+    }
 
-    let mut interval = tokio::time::interval(Duration::from_secs(state.config.poll_interval_secs));
-    let mut seq = 0u16;
+    let icmp6 = state.config.services.iter()
+        .any(|s| !s.paused && s.check == "ping" && s.family.as_deref() == Some("v6"))
+        .then(icmp6_client);
+
+    let mut interval = tokio::time::interval(Duration::from_secs(state.config.tick_secs()));
+    // When each target is next due. Stepped from the tick's scheduled instant, not
+    // the wall clock, so a tick landing exactly on it isn't skipped for jitter.
+    let mut next_due: HashMap<String, Instant> = HashMap::new();
+    // Latest status per key, for quorums whose members run on their own intervals.
+    let mut latest: HashMap<String, &'static str> = HashMap::new();
+    let mut last_purge: Option<Instant> = None;
+    let mut icmp_ids = IcmpIds::new();
+    // Last status per key and when it started — a change is a notification.
+    // Seeded by the first round, so a restart doesn't announce every target.
+    let mut last_state: HashMap<String, (&'static str, Instant)> = HashMap::new();
+    // Transitions held back by quiet_hours, for the summary when the window ends.
+    let mut quiet_held: Vec<notify::Transition> = Vec::new();
+    // Email alerts: per target in a DOWN state, its DOWN transition and whether the
+    // mail went out — sent once alert_after_n checks in a row failed; the recovery is
+    // only mailed after a DOWN mail.
+    let mut email_runs: HashMap<String, (notify::Transition, bool)> = HashMap::new();
+    let alert_after_n = state.config.mailer.as_ref()
+        .filter(|m| m.alert_on_change)
+        .map(|m| m.alert_after_n.max(1) as usize);
+    // Each target's latest statuses, newest first — enough for debounced_state and
+    // alert_after_n. last_state holds the debounced state these are compared against.
+    let (down_n, up_n) = (state.config.down_after_checks as usize, state.config.up_after_checks as usize);
+    let keep = down_n.max(up_n).max(alert_after_n.unwrap_or(0));
+    let mut recent: HashMap<String, VecDeque<&'static str>> = HashMap::new();
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    // Remaining grace_polls per target that had no history at startup; dropped once
+    // the target is seen UP or its grace runs out.
+    let mut grace: HashMap<String, u32> = HashMap::new();
+    // Hosts given by name: the address last resolved and when. Re-resolved once
+    // older than HOST_RESOLVE_SECS, or after a round where the ping failed.
+    let mut host_ips: HashMap<String, (IpAddr, Instant)> = HashMap::new();
+    if state.config.grace_polls > 0 {
+        let db = state.read_db.lock().unwrap();
+        let keys = state.config.hosts.iter().filter(|h| !h.paused).flat_map(|h| h.targets()).cloned()
+            .chain(state.config.services.iter().filter(|s| !s.paused).map(|s| format!("svc:{}", s.label)));
+        for key in keys {
+            if query_latest_status(&db, &key).0 == NO_DATA {
+                grace.insert(key, state.config.grace_polls);
+            }
+        }
+    }
 
     loop {
-        interval.tick().await;
+        let tick = interval.tick().await.into_std();
+        let mut due = |key: &str| match next_due.get(key) {
+            Some(&at) if at > tick => false,
+            _ => {
+                next_due.insert(key.to_string(), tick + Duration::from_secs(state.config.interval_for(key)));
+                true
+            }
+        };
 
         let mut rows: Vec<(String, String, &'static str, Option<f64>)> = Vec::new();
         let mut new_resolved: Vec<(String, Option<String>)> = Vec::new();
-
-        // LAN hosts — sequential, one ping at a time.
-        // Avoids ICMP bursts that overwhelm embedded routers; avoids identifier
-        // collisions (all concurrent pingers shared PingIdentifier(0xAB)); avoids
-        // latency inflation from sharing one event-loop thread across N sockets.
-        // Each check yields at .await so the HTTP runtime stays responsive.
-        // PingIdentifier(seq) changes each cycle — stale replies from a previous
-        // timed-out cycle can't be mistaken for the current one.
-        for host in &state.config.hosts {
-            let addr: IpAddr = host.addr.parse().unwrap_or_else(|e| {
-                panic!("Invalid host address '{}': {e}", host.addr)
-            });
-            let mut pinger = client.pinger(addr, PingIdentifier(seq)).await;
-            pinger.timeout(Duration::from_secs(state.config.ping_timeout_secs));
-            let payload = [0u8; 56];
-            let (status, latency_ms) = match pinger.ping(PingSequence(seq), &payload).await {
-                Ok((_pkt, dur)) => ("UP", Some(dur.as_secs_f64() * 1000.0)),
-                Err(_) => ("DOWN", None),
+        let mut new_errors: HashMap<String, String> = HashMap::new();
+        let mut cert_expiry: HashMap<String, i64> = HashMap::new();
+
+        // Build the round's checks from the targets due: LAN hosts first, then
+        // services. Group cards contribute each member; plain hosts their own addr.
+        // Identifiers and sequences are per target — see IcmpIds.
+        let mut jobs: Vec<(String, Option<&str>)> = Vec::new();
+        let mut checks: Vec<(i32, LocalBoxFuture<'_, CheckResult>)> = Vec::new();
+        let hosts = state.config.hosts.iter().filter(|h| !h.paused);
+        host_ips.retain(|_, (_, at)| at.elapsed() < Duration::from_secs(HOST_RESOLVE_SECS));
+        for (host, host_addr) in hosts.flat_map(|h| h.targets().iter().map(move |a| (h, a))) {
+            if !due(host_addr) {
+                continue;
+            }
+            let timeout_secs = host.timeout_secs.unwrap_or(state.config.ping_timeout_secs);
+            let route = host.icmp_route(&state.config);
+            let client = match clients.get(&route) {
+                None => &default_client,
+                Some(Ok(client)) => client,
+                Some(Err(e)) => {
+                    let e = e.clone();
+                    jobs.push((host_addr.clone(), None));
+                    checks.push((host.priority, Box::pin(async move { CheckResult::down(e, None) })));
+                    continue;
+                }
+            };
+            // The client's socket family decides which address a name may resolve to.
+            let family = match route.0.and_then(|s| s.parse::<IpAddr>().ok()) {
+                Some(IpAddr::V6(_)) => "v6",
+                _ => "v4",
             };
-            rows.push((host.addr.clone(), Local::now().to_rfc3339(), status, latency_ms));
-        }
-
-        // External services — sequential, same reasoning.
-        for svc in &state.config.services {
-            let (up, latency_ms, resolved_ip) = match svc.check.as_str() {
-                "ping" => check_ping(&client, &svc.target, seq, state.config.ping_timeout_secs).await,
-                "dns"  => check_dns(&svc.target, state.config.ping_timeout_secs).await,
-                "tcp"  => check_tcp(&svc.target, state.config.ping_timeout_secs).await,
-                other  => {
-                    eprintln!("Unknown check type '{}' for service '{}'", other, svc.label);
-                    (false, None, None)
+            let cached = host_ips.get(host_addr).map(|&(ip, _)| ip);
+            let (ident, seq) = icmp_ids.next(host_addr);
+            jobs.push((host_addr.clone(), None));
+            checks.push((host.priority, Box::pin(check_host(client, host_addr, cached, family, ident, seq, timeout_secs))));
+        }
+        for svc in state.config.services.iter().filter(|s| !s.paused) {
+            if svc.check == "quorum" || !due(&format!("svc:{}", svc.label)) {
+                continue;
+            }
+            let timeout_secs = svc.timeout_secs.unwrap_or(state.config.ping_timeout_secs);
+            // One attempt per target; futures are lazy, so a failover service only
+            // runs the next one after the previous has failed.
+            let mut attempts: Vec<LocalBoxFuture<'_, CheckResult>> = Vec::new();
+            for target in &svc.targets {
+                attempts.push(match svc.check.as_str() {
+                    "ping" => {
+                        let (ident, seq) = icmp_ids.next(&format!("svc:{}", svc.label));
+                        let client = match (svc.family.as_deref(), &icmp6) {
+                            (Some("v6"), Some(c6)) => c6,
+                            _ => &default_client,
+                        };
+                        Box::pin(check_ping(client, target, svc.family.as_deref(), ident, seq, timeout_secs))
+                    }
+                    "dns"  => {
+                        let (name, qtype) = svc.dns_question(target);
+                        let expect = svc.expect_answer.as_deref().and_then(|ip| ip.parse().ok());
+                        Box::pin(async move { check_dns(target, &name, qtype, expect, timeout_secs).await })
+                    }
+                    "tcp"  => Box::pin(check_tcp(target, svc.family.as_deref(), timeout_secs)),
+                    "http" => Box::pin(check_http(target, svc, timeout_secs)),
+                    "tls"  => Box::pin(check_tls(target, svc.allow_self_signed, svc.cert_warn_days.unwrap_or(14), timeout_secs)),
+                    _ => break,
+                });
+            }
+            let check: LocalBoxFuture<'_, CheckResult> = match svc.check.as_str() {
+                "exec" if state.config.allow_exec => {
+                    Box::pin(check_exec(&svc.command, svc.timeout_secs.unwrap_or(state.config.exec_timeout_secs)))
                 }
+                // Unknown type or no target: reported once by Config::validate at startup
+                // and rendered as misconfigured — don't record a DOWN that isn't the target's fault.
+                _ if attempts.is_empty() => continue,
+                _ if attempts.len() == 1 => attempts.remove(0),
+                _ => Box::pin(check_failover(&svc.targets, attempts)),
             };
+            jobs.push((format!("svc:{}", svc.label), Some(svc.label.as_str())));
+            checks.push((svc.priority, check));
+        }
+        // Synthetic quorum services are derived below, once this round's checks have run.
+        let quorums: Vec<&Service> = state.config.services.iter()
+            .filter(|s| !s.paused && s.check == "quorum" && due(&format!("svc:{}", s.label)))
+            .collect();
+        if jobs.is_empty() && quorums.is_empty() {
+            // Nothing due on this tick: no round, but the loop is alive.
+            if std::env::var_os("WATCHDOG_USEC").is_some() {
+                sd_notify("WATCHDOG=1");
+            }
+            continue;
+        }
+
+        // At most max_concurrent_checks in flight. The default of 1 is the sequential
+        // behaviour v1.13.0 settled on (see FUTURE_WORK.md): no ICMP bursts at
+        // embedded routers, no latency inflation from N sockets sharing this one
+        // event-loop thread. Each check yields at .await so HTTP stays responsive.
+        // The round deadline bounds a hung target: whatever hasn't finished by then
+        // is recorded DOWN so the DB write and page render still happen.
+        // Start order is by priority (stable, so config order within a priority);
+        // results still land at their job's index.
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(state.config.round_deadline_secs);
+        let mut results: Vec<Option<(String, CheckResult)>> = (0..checks.len()).map(|_| None).collect();
+        let mut queued: Vec<(usize, (i32, LocalBoxFuture<'_, CheckResult>))> = checks.into_iter().enumerate().collect();
+        queued.sort_by_key(|(_, (priority, _))| std::cmp::Reverse(*priority));
+        let mut pending = futures_util::stream::iter(queued.into_iter().map(|(i, (_, check))| async move {
+            let result = check.await;
+            (i, Local::now().to_rfc3339(), result)
+        }))
+        .buffer_unordered(state.config.max_concurrent_checks.max(1));
+        loop {
+            match tokio::time::timeout_at(deadline, pending.next()).await {
+                Ok(Some((i, ts, result))) => results[i] = Some((ts, result)),
+                Ok(None) => break,
+                Err(_) => {
+                    let unfinished = results.iter().filter(|r| r.is_none()).count();
+                    warn!(
+                        "Poll round hit round_deadline_secs ({}s): {unfinished} check(s) recorded DOWN",
+                        state.config.round_deadline_secs,
+                    );
+                    break;
+                }
+            }
+        }
+        drop(pending);
+
+        for ((key, svc_label), result) in jobs.into_iter().zip(results) {
+            let (ts, result) = result.unwrap_or_else(|| {
+                (Local::now().to_rfc3339(), CheckResult::down("round deadline exceeded", None))
+            });
+            let status = if result.up { "UP" } else { "DOWN" };
+            if let Some(err) = result.error {
+                new_errors.insert(key.clone(), err);
+            }
+            if let Some(label) = svc_label {
+                new_resolved.push((label.to_string(), result.resolved_ip));
+            } else if key.parse::<IpAddr>().is_err() {
+                match result.resolved_ip.as_deref().and_then(|ip| ip.parse().ok()) {
+                    Some(ip) if result.up => {
+                        host_ips.entry(key.clone()).or_insert((ip, Instant::now()));
+                    }
+                    _ => { host_ips.remove(&key); }
+                }
+                new_resolved.push((key.clone(), result.resolved_ip));
+            }
+            if let Some(expiry) = result.cert_expiry {
+                cert_expiry.insert(key.clone(), expiry);
+            }
+            rows.push((key, ts, status, result.latency_ms));
+        }
+
+        // Synthetic quorum services: a member counts if its latest check was UP —
+        // members may run on their own intervals. A paused or misconfigured member
+        // is never checked, so never props the light up.
+        for (key, _, status, _) in &rows {
+            latest.insert(key.clone(), status);
+        }
+        for svc in quorums {
+            let up = svc.members.iter()
+                .map(|m| state.config.member_key(m))
+                .filter(|k| latest.get(k) == Some(&"UP"))
+                .count();
+            let need = svc.quorum_needed();
             let key = format!("svc:{}", svc.label);
-            let status = if up { "UP" } else { "DOWN" };
-            rows.push((key, Local::now().to_rfc3339(), status, latency_ms));
-            new_resolved.push((svc.label.clone(), resolved_ip));
+            let status = if up >= need && need > 0 { "UP" } else { "DOWN" };
+            if status == "DOWN" {
+                new_errors.insert(key.clone(), format!("{up}/{} members up, need {need}", svc.members.len()));
+            }
+            rows.push((key, Local::now().to_rfc3339(), status, None));
+        }
+
+        for (key, _, status, _) in rows.iter_mut() {
+            let Some(left) = grace.get_mut(key.as_str()) else { continue };
+            if *status == "DOWN" && *left > 0 {
+                *status = PENDING;
+                *left -= 1;
+            } else {
+                grace.remove(key.as_str());
+            }
+        }
+
+        for (key, ..) in rows.iter().filter(|(_, _, s, _)| *s == "DOWN") {
+            warn!("{key} DOWN: {}", new_errors.get(key).map_or("check failed", String::as_str));
+        }
+
+        if let Some(path) = &state.config.check_log {
+            append_check_log(path, state.config.check_log_max_bytes, &rows, &new_errors);
         }
 
         // Update resolved IPs
@@ -420,30 +1793,157 @@ async fn poll_loop(state: Arc<AppState>) {
                 ips.insert(label, ip);
             }
         }
+        // Failure reasons are only meaningful for a target's latest check — drop those
+        // of every target checked this round, so a recovered target's stale reason
+        // disappears while targets that weren't due keep theirs.
+        {
+            let mut errors = state.check_errors.lock().unwrap();
+            errors.retain(|key, _| !rows.iter().any(|(k, ..)| k == key));
+            errors.extend(new_errors);
+        }
+
+        let now = Instant::now();
+        let time_of_day = state.config.local_now().time();
+        let mut events: Vec<(&str, &str, &'static str, &'static str, i64)> = Vec::new();
+        for (key, ts, status, latency_ms) in &rows {
+            // Grace-period failures aren't a state: the first real one seeds it.
+            if *status == PENDING {
+                continue;
+            }
+            let history = recent.entry(key.clone()).or_default();
+            history.push_front(status);
+            history.truncate(keep);
+            let history: &[&str] = history.make_contiguous();
+            match last_state.get_mut(key) {
+                Some((prev, since)) if debounced_state(history, prev, down_n, up_n) != *prev => {
+                    let lasted = now - *since;
+                    // This round's rows aren't stored yet: an outage starting now starts at `ts`.
+                    let acked = *status == "DOWN" && {
+                        let db = state.db.lock().unwrap();
+                        let down_since = query_down_since(&db, key).unwrap_or_else(|| ts.clone());
+                        query_ack(&db, &state.config, key, &down_since).is_some()
+                    };
+                    let t = notify::Transition {
+                        key: key.clone(),
+                        label: state.config.label_for(key),
+                        status,
+                        previous: prev,
+                        lasted,
+                        timestamp: ts.clone(),
+                        latency_ms: *latency_ms,
+                        uptime_1h: query_card_uptime(&state.db.lock().unwrap(), std::slice::from_ref(key), 60),
+                        acked,
+                    };
+                    if alert_after_n.is_some() {
+                        if *status == "DOWN" {
+                            email_runs.insert(key.clone(), (t.clone(), false));
+                        } else if let Some((_, true)) = email_runs.remove(key) {
+                            if !state.config.is_quiet(key, time_of_day) {
+                                notify::dispatch_email(&state.config, &t);
+                            }
+                        }
+                    }
+                    if state.config.is_quiet(key, time_of_day) {
+                        quiet_held.push(t);
+                    } else {
+                        notify::dispatch(&state.config, &http, &t);
+                    }
+                    events.push((key, ts, status, prev, lasted.as_secs() as i64));
+                    *prev = status;
+                    *since = now;
+                }
+                Some(_) => {}
+                None => { last_state.insert(key.clone(), (status, now)); }
+            }
+            if let (Some(n), Some((t, mailed))) = (alert_after_n, email_runs.get_mut(key)) {
+                let downs = history.iter().take_while(|s| **s == "DOWN").count();
+                if !*mailed && downs >= n && !state.config.is_quiet(key, time_of_day) {
+                    notify::dispatch_email(&state.config, t);
+                    *mailed = true;
+                }
+            }
+        }
+
+        if let Some(q) = &state.config.quiet_hours {
+            if !quiet_held.is_empty() && !q.contains(time_of_day) {
+                if q.summary {
+                    notify::dispatch_summary(&state.config, &http, &quiet_held);
+                }
+                quiet_held.clear();
+            }
+        }
 
-        // Single transaction: all INSERTs + purge (one fsync)
+        // Single transaction: all INSERTs, plus the purge when it's due (one fsync).
+        // A failure (disk full, database locked) loses this round's rows, not the poller.
         let cutoff = (Local::now() - chrono::Duration::days(state.config.retention_days)).to_rfc3339();
-        {
+        let purge = last_purge.is_none_or(|t| t.elapsed() >= Duration::from_secs(PURGE_INTERVAL_SECS));
+        let written = (|| -> rusqlite::Result<()> {
             let mut db = state.db.lock().unwrap();
-            let tx = db.transaction().unwrap();
+            let tx = db.transaction()?;
+            // Statements are prepared once per round, not once per row.
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO ping_results (host, timestamp, status, latency_ms, latency_us, cert_expiry, kind) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
             for (host, now, status, latency_ms) in &rows {
+                // Exactly one latency column is filled; readers COALESCE the two.
+                let (ms, us) = if state.config.latency_us {
+                    (None, latency_ms.map(|ms| (ms * 1000.0).round() as i64))
+                } else {
+                    (*latency_ms, None)
+                };
+                insert.execute(params![host, now, status, ms, us, cert_expiry.get(host), state.config.kind_for(host)])?;
+            }
+            drop(insert);
+            let mut insert_event = tx.prepare_cached(
+                "INSERT INTO events (host, timestamp, status, previous, lasted_secs) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (host, ts, status, previous, lasted_secs) in &events {
+                insert_event.execute(params![host, ts, status, previous, lasted_secs])?;
+            }
+            drop(insert_event);
+            if purge {
                 tx.execute(
-                    "INSERT INTO ping_results (host, timestamp, status, latency_ms) VALUES (?1, ?2, ?3, ?4)",
-                    params![host, now, status, latency_ms],
-                ).unwrap();
-            }
-            tx.execute(
-                "DELETE FROM ping_results WHERE timestamp < ?1",
-                params![cutoff],
-            ).unwrap();
-            tx.commit().unwrap();
+                    "DELETE FROM ping_results WHERE timestamp < ?1",
+                    params![cutoff],
+                )?;
+                tx.execute(
+                    "DELETE FROM events WHERE timestamp < ?1",
+                    params![cutoff],
+                )?;
+            }
+            tx.commit()?;
+            if purge {
+                // No-op unless the database was created with auto_vacuum=INCREMENTAL.
+                db.execute_batch("PRAGMA incremental_vacuum;")?;
+            }
             if state.config.wal_mode {
-                db.execute_batch("PRAGMA wal_checkpoint(PASSIVE);").unwrap();
+                db.execute_batch("PRAGMA wal_checkpoint(PASSIVE);")?;
             }
+            Ok(())
+        })();
+        match written {
+            Ok(()) if purge => last_purge = Some(Instant::now()),
+            Ok(()) => {}
+            Err(e) => error!("Failed to record poll round: {e}"),
         }
 
+        if let Some(g) = &state.config.graphite {
+            let points: Vec<_> = rows.iter()
+                .filter(|(_, _, s, _)| *s != PENDING)
+                .map(|(k, _, s, l)| (k.clone(), *s, *l))
+                .collect();
+            graphite::push(g, &points, Local::now().timestamp());
+        }
+        state.last_poll.store(Local::now().timestamp(), Ordering::Relaxed);
         pre_render_and_advance(&state);
-        seq = seq.wrapping_add(1);
+        // After the pre-render, so a page fetched on this message is already current.
+        // Err just means nobody is listening.
+        let _ = state.updates.send(Local::now().timestamp());
+        // Watchdog keepalive once per completed round — a wedged poll loop stops
+        // these, so WatchdogSec must exceed poll_interval_secs plus worst-case round time.
+        if std::env::var_os("WATCHDOG_USEC").is_some() {
+            sd_notify("WATCHDOG=1");
+        }
     }
 }
 
@@ -452,8 +1952,9 @@ async fn poll_loop(state: Arc<AppState>) {
 fn render_page(state: &AppState, ui: &UiCookie, refresh_secs: u64) -> String {
     let db = state.read_db.lock().unwrap();
     let resolved_ips = state.resolved_ips.lock().unwrap().clone();
+    let errors = state.check_errors.lock().unwrap().clone();
 
-    let services_html = render_services(&db, &state.config.services, ui, &resolved_ips);
+    let services_html = render_services(&db, &state.config, &state.config.services, ui, &resolved_ips, &errors, false, false, Some(&state.started_at));
     let name = &state.config.name;
 
     let theme_attr = match ui.theme.as_deref() {
@@ -461,6 +1962,7 @@ fn render_page(state: &AppState, ui: &UiCookie, refresh_secs: u64) -> String {
         Some("light") => " data-theme=\"light\"",
         _             => "",
     };
+    let theme_attr = if ui.colorblind { format!("{theme_attr} data-cb") } else { theme_attr.to_string() };
 
     let heading_html = if name == "pi-glass" {
         format!(
@@ -483,23 +1985,24 @@ fn render_page(state: &AppState, ui: &UiCookie, refresh_secs: u64) -> String {
         heading_html = heading_html,
         style_head = style_head,
         services_html = services_html,
-        favicon_svg_route = state.favicon_svg_route,
+        status_icon_route = format!("/status.svg?d={}", count_down(&db, &state.config)),
         apple_touch_route = state.apple_touch_route,
         manifest_route = state.manifest_route,
     );
 
     for host in &state.config.hosts {
         let user_open = ui.open_hosts.as_ref().map(|set| set.contains(&host.addr));
-        html.push_str(&render_host(&db, host, user_open));
+        let resolved_ip = resolved_ips.get(&host.addr).and_then(|o| o.as_deref());
+        html.push_str(&render_host(&db, &state.config, host, user_open, resolved_ip, &errors, true, Some(&state.started_at)));
     }
 
     if let Some(ref toml) = state.config_toml {
-        html.push_str(r#"<details class="config-card" open><summary class="config-summary">config.toml — save this file to get started</summary><pre class="config-block">"#);
+        html.push_str(r#"<details class="config-card" open><summary class="config-summary">config.toml — save this file to get started<a class="config-download" href="/config.toml" download>Download</a></summary><pre class="config-block">"#);
         html.push_str(&html_escape(toml));
         html.push_str("</pre></details>");
     }
 
-    html.push_str(&format!(r##"<footer>Made with &#10084;&#65039; by <a href="mailto:david@connol.ly">David Connolly</a> &amp; <a href="https://claude.ai">Claude</a> &middot; <a href="https://github.com/slartibardfast/pi-glass">pi-glass v{VERSION}</a></footer>"##));
+    html.push_str(&render_footer(&state.config));
     html.push_str(&format!(r#"<script src="/static/{}.js"></script>"#, state.js_hash));
     html.push_str("</body></html>");
 
@@ -562,10 +2065,27 @@ fn pre_render_and_advance(state: &AppState) {
     state.poll_generation.store(new_gen, Ordering::Release);
 }
 
+/// Delete every check and event row for exactly `key`, in one transaction. Waits out
+/// a running server's write lock rather than failing.
+fn reset_history(mut conn: Connection, key: &str) -> rusqlite::Result<(usize, usize)> {
+    conn.busy_timeout(Duration::from_secs(30))?;
+    let tx = conn.transaction()?;
+    let checks = tx.execute("DELETE FROM ping_results WHERE host = ?1", params![key])?;
+    let events = tx.execute("DELETE FROM events WHERE host = ?1", params![key])?;
+    tx.commit()?;
+    Ok((checks, events))
+}
+
 // --- HTTP handler ---
 
+#[derive(Deserialize)]
+struct PageParams {
+    open: Option<String>,
+}
+
 async fn handler(
     State(state): State<Arc<AppState>>,
+    Query(p): Query<PageParams>,
     headers: axum::http::HeaderMap,
 ) -> axum::response::Response {
     use axum::http::{header, StatusCode};
@@ -576,6 +2096,27 @@ async fn handler(
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
+    // `?open=all|down` overrides the cookie's open state for a shareable view. It
+    // skips the page cache and the cookie pre-render list — it's a one-off render.
+    if let Some(open) = p.open.as_deref() {
+        let mut ui = match open {
+            "all"  => all_open_ui(&state.config),
+            "down" => down_open_ui(&state.read_db.lock().unwrap(), &state.config),
+            _      => return (StatusCode::BAD_REQUEST, "open must be all or down").into_response(),
+        };
+        let cookie = parse_ui_cookie(cookie_str);
+        ui.theme = cookie.theme;
+        ui.colorblind = cookie.colorblind;
+        let refresh = state.effective_refresh_secs.load(Ordering::Acquire) as u64;
+        return (
+            [
+                (header::CACHE_CONTROL, "no-cache"),
+                (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            ],
+            render_page(&state, &ui, refresh),
+        ).into_response();
+    }
+
     let generation = state.poll_generation.load(Ordering::Acquire);
 
     let mut hasher = DefaultHasher::new();
@@ -647,3 +2188,568 @@ async fn handler(
         body,
     ).into_response()
 }
+
+// --- Metrics ---
+
+/// Label value escaping per the Prometheus text format: `\`, `"` and newline.
+fn metric_label(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// `GET /metrics` — Prometheus text exposition, or OpenMetrics when the scraper's
+/// `Accept` asks for `application/openmetrics-text`. Only latest-status lookups, so
+/// it's as cheap as a page render and stays outside the rate limiter.
+async fn metrics(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    use axum::http::header;
+    use axum::response::IntoResponse;
+    use std::fmt::Write as _;
+
+    let openmetrics = headers.get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|a| a.contains("application/openmetrics-text"));
+
+    let cfg = &state.config;
+    let keys: Vec<String> = cfg.hosts.iter().filter(|h| !h.paused).flat_map(|h| h.targets()).cloned()
+        .chain(cfg.services.iter()
+            .filter(|s| !s.paused && cfg.is_runnable(s))
+            .map(|s| format!("svc:{}", s.label)))
+        .collect();
+    let latest: Vec<(String, Option<f64>)> = {
+        let db = state.read_db.lock().unwrap();
+        keys.iter().map(|k| query_latest_status(&db, k)).collect()
+    };
+
+    let mut out = String::new();
+    out.push_str("# HELP pi_glass_build_info Build information.\n# TYPE pi_glass_build_info gauge\n");
+    writeln!(out, "pi_glass_build_info{{version=\"{}\"}} 1", env!("CARGO_PKG_VERSION")).unwrap();
+    out.push_str("# HELP pi_glass_last_poll_timestamp_seconds Unix time the last poll round completed.\n# TYPE pi_glass_last_poll_timestamp_seconds gauge\n");
+    writeln!(out, "pi_glass_last_poll_timestamp_seconds {}", state.last_poll.load(Ordering::Relaxed)).unwrap();
+    out.push_str("# HELP pi_glass_up Whether the target's latest check was UP (1) or DOWN (0).\n# TYPE pi_glass_up gauge\n");
+    for (key, (status, _)) in keys.iter().zip(&latest) {
+        if status == "UP" || status == "DOWN" {
+            writeln!(out, "pi_glass_up{{key=\"{}\",label=\"{}\"}} {}",
+                metric_label(key), metric_label(&cfg.label_for(key)), u8::from(status == "UP")).unwrap();
+        }
+    }
+    out.push_str("# HELP pi_glass_latency_seconds Round-trip time of the target's latest successful check.\n# TYPE pi_glass_latency_seconds gauge\n");
+    for (key, (_, latency)) in keys.iter().zip(&latest) {
+        if let Some(ms) = latency {
+            writeln!(out, "pi_glass_latency_seconds{{key=\"{}\",label=\"{}\"}} {}",
+                metric_label(key), metric_label(&cfg.label_for(key)), ms / 1000.0).unwrap();
+        }
+    }
+
+    let content_type = if openmetrics {
+        out.push_str("# EOF\n");
+        "application/openmetrics-text; version=1.0.0; charset=utf-8"
+    } else {
+        "text/plain; version=0.0.4; charset=utf-8"
+    };
+    ([(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, "no-cache")], out).into_response()
+}
+
+// --- JSON API ---
+
+#[derive(Deserialize)]
+struct HistoryParams {
+    key: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    buckets: Option<usize>,
+}
+
+/// Columnar layout — parallel arrays drop straight into most charting libs.
+#[derive(Serialize)]
+struct HistoryResponse {
+    key: String,
+    from: i64,
+    to: i64,
+    bucket_secs: i64,
+    t: Vec<i64>,
+    avg_ms: Vec<Option<f64>>,
+    uptime: Vec<Option<f64>>,
+}
+
+/// `GET /api/history?key=...&from=...&to=...&buckets=N` — a target's history
+/// downsampled into N buckets. `from`/`to` are unix seconds (default: last 24h).
+async fn api_history(
+    State(state): State<Arc<AppState>>,
+    Query(p): Query<HistoryParams>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    if !state.config.has_target(&p.key) {
+        return (StatusCode::NOT_FOUND, "unknown key").into_response();
+    }
+    let to = p.to.unwrap_or_else(|| Local::now().timestamp());
+    let from = p.from.unwrap_or(to - 86400);
+    if from >= to {
+        return (StatusCode::BAD_REQUEST, "from must be before to").into_response();
+    }
+    let buckets = p.buckets.unwrap_or(100).clamp(1, 1000);
+
+    let (bucket_secs, rows) = {
+        let db = state.read_db.lock().unwrap();
+        query_history_buckets(&db, &p.key, from, to, buckets)
+    };
+    let resp = HistoryResponse {
+        key: p.key,
+        from,
+        to,
+        bucket_secs,
+        t: rows.iter().map(|b| b.start).collect(),
+        avg_ms: rows.iter().map(|b| b.avg_ms).collect(),
+        uptime: rows.iter().map(|b| b.uptime).collect(),
+    };
+    ([(header::CACHE_CONTROL, "no-cache")], axum::Json(resp)).into_response()
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    host: String,
+    hours: Option<i64>,
+}
+
+/// `GET /export.csv?host=...&hours=N` — a target's raw checks over the last N hours
+/// (default 24, at most `retention_days`) as a CSV download.
+async fn export_csv(
+    State(state): State<Arc<AppState>>,
+    Query(p): Query<ExportParams>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    if !state.config.has_target(&p.host) {
+        return (StatusCode::NOT_FOUND, "unknown host").into_response();
+    }
+    let hours = p.hours.unwrap_or(24).clamp(1, state.config.retention_days.max(1) * 24);
+    let cutoff = (Local::now() - chrono::Duration::hours(hours)).to_rfc3339();
+    let csv = {
+        let db = state.read_db.lock().unwrap();
+        query_checks_csv(&db, &p.host, &cutoff)
+    };
+    // "svc:Google DNS" -> pi-glass-svc_Google_DNS.csv
+    let name: String = p.host.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') { c } else { '_' })
+        .collect();
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"pi-glass-{name}.csv\"")),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        csv,
+    ).into_response()
+}
+
+#[derive(Deserialize)]
+struct SparklineParams {
+    key: String,
+}
+
+/// Bars oldest first; `null` is a DOWN check. Stats are over UP checks only and
+/// absent when every check was DOWN.
+#[derive(Serialize)]
+struct SparklineResponse {
+    key: String,
+    pad: usize,
+    bars: Vec<Option<u32>>,
+    checks: usize,
+    avg_ms: Option<f64>,
+    stddev_ms: Option<f64>,
+    min_ms: Option<f64>,
+    max_ms: Option<f64>,
+}
+
+/// `GET /api/sparkline?key=...` — the dashboard's sparkline for a target as the
+/// same normalized 0–100 bars, for drawing identical sparklines elsewhere.
+async fn api_sparkline(
+    State(state): State<Arc<AppState>>,
+    Query(p): Query<SparklineParams>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    if !state.config.has_target(&p.key) {
+        return (StatusCode::NOT_FOUND, "unknown key").into_response();
+    }
+    let checks = {
+        let db = state.read_db.lock().unwrap();
+        sparkline_checks(&db, &state.config, &p.key)
+    };
+    let data = normalize_sparkline(&checks);
+    let resp = SparklineResponse {
+        key: p.key,
+        pad: data.pad,
+        checks: data.bars.len(),
+        bars: data.bars,
+        avg_ms: data.stats.map(|s| s.avg),
+        stddev_ms: data.stats.map(|s| s.stddev),
+        min_ms: data.stats.map(|s| s.min),
+        max_ms: data.stats.map(|s| s.max),
+    };
+    ([(header::CACHE_CONTROL, "no-cache")], axum::Json(resp)).into_response()
+}
+
+#[derive(Deserialize)]
+struct EventsParams {
+    key: Option<String>,
+    since: Option<i64>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct EventJson {
+    key: String,
+    t: i64,
+    status: String,
+    previous: String,
+    lasted_secs: i64,
+}
+
+/// `GET /api/events?since=<unix>&key=...&limit=N` — recorded UP/DOWN transitions,
+/// newest first. `since` defaults to the last 24h, `limit` to 100 (max 1000).
+async fn api_events(
+    State(state): State<Arc<AppState>>,
+    Query(p): Query<EventsParams>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    if let Some(key) = &p.key {
+        if !state.config.has_target(key) {
+            return (StatusCode::NOT_FOUND, "unknown key").into_response();
+        }
+    }
+    let since = p.since.unwrap_or_else(|| Local::now().timestamp() - 86400);
+    let limit = p.limit.unwrap_or(100).clamp(1, 1000);
+
+    let events = {
+        let db = state.read_db.lock().unwrap();
+        query_events(&db, p.key.as_deref(), since, limit)
+    };
+    let resp: Vec<EventJson> = events.into_iter()
+        .map(|e| EventJson { key: e.key, t: e.t, status: e.status, previous: e.previous, lasted_secs: e.lasted_secs })
+        .collect();
+    ([(header::CACHE_CONTROL, "no-cache")], axum::Json(resp)).into_response()
+}
+
+/// `GET /events` — Server-Sent Events, one `round` event (data: its unix time) per
+/// completed poll round, telling the dashboard to fetch the fresh page. A listener
+/// that falls behind skips to the newest round.
+async fn live_updates(
+    State(state): State<Arc<AppState>>,
+) -> axum::response::sse::Sse<impl futures_util::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>> {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let stream = futures_util::stream::unfold(state.updates.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(t) => return Some((Ok(Event::default().event("round").data(t.to_string())), rx)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Constant-time comparison for secrets — no early exit on the first differing byte.
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Deserialize)]
+struct ProbeParams {
+    target: String,
+    #[serde(rename = "type", default = "default_probe_type")]
+    check: String,
+}
+
+fn default_probe_type() -> String { "tcp".to_string() }
+
+#[derive(Serialize)]
+struct ProbeResponse {
+    target: String,
+    #[serde(rename = "type")]
+    check: String,
+    up: bool,
+    latency_ms: Option<f64>,
+    resolved_ip: Option<String>,
+    error: Option<String>,
+}
+
+/// `GET /ping?target=...&type=tcp|ping|dns` — one-off check with the same functions
+/// the poll loop uses; the result is returned, never stored. Bearer-token gated and
+/// rate-limited: it's a network probe on behalf of the caller.
+async fn probe(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(p): Query<ProbeParams>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    let Some(token) = &state.config.probe_token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let authorized = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| ct_eq(t.as_bytes(), token.as_bytes()));
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], "unauthorized").into_response();
+    }
+
+    let timeout_secs = state.config.ping_timeout_secs;
+    let result = match p.check.as_str() {
+        "tcp" => check_tcp(&p.target, None, timeout_secs).await,
+        "dns" => check_dns(&p.target, DNS_DEFAULT_NAME, 1, None, timeout_secs).await,
+        "ping" => {
+            // surge_ping's Client is !Send, so it can't live in an axum handler future;
+            // give the probe its own short-lived current_thread runtime.
+            let target = p.target.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+                    .block_on(async {
+                        let client = match icmp_client(None, None) {
+                            Ok(client) => client,
+                            Err(e) => return CheckResult::down(e, None),
+                        };
+                        let ident = PingIdentifier(std::process::id() as u16 ^ 0x8000);
+                        check_ping(&client, &target, None, ident, PingSequence(0), timeout_secs).await
+                    })
+            }).await;
+            match res {
+                Ok(r) => r,
+                Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+        _ => return (StatusCode::BAD_REQUEST, "type must be tcp, ping or dns").into_response(),
+    };
+    let resp = ProbeResponse {
+        target: p.target,
+        check: p.check,
+        up: result.up,
+        latency_ms: result.latency_ms,
+        resolved_ip: result.resolved_ip,
+        error: result.error,
+    };
+    ([(header::CACHE_CONTROL, "no-store")], axum::Json(resp)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls_pki_types::{pem::PemObject, CertificateDer};
+
+    /// Self-signed P-256 leaf, notAfter 2035-01-01 as UTCTime.
+    const CERT_UTC_TIME: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBaTCCAQ6gAwIBAgIBATAKBggqhkjOPQQDAjATMREwDwYDVQQDDAh1dGMudGVz
+dDAeFw0yNTAxMDEwMDAwMDBaFw0zNTAxMDEwMDAwMDBaMBMxETAPBgNVBAMMCHV0
+Yy50ZXN0MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEZB2did5HzhQWxgyWIBCV
+lF3WVi2VhTbXWUkIT5ZdukmGfAVtOPi9tCZJUyCe+x1zlgYuAl+Aj5feC0ROOedW
+MaNTMFEwHQYDVR0OBBYEFM0n9RW8zmW1oaalgMydh/ukFYWpMB8GA1UdIwQYMBaA
+FM0n9RW8zmW1oaalgMydh/ukFYWpMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0E
+AwIDSQAwRgIhAM+mTszjgfeFugFHhuP1VfRGC8jevwLXnr+2B/0bylBtAiEA4BUz
+NLu/d7Kvy2etyQlq0QkkOlymwLGDKvAWKd/sbRM=
+-----END CERTIFICATE-----";
+
+    /// Self-signed P-256 leaf, notAfter 2060-01-01 — past 2049, so GeneralizedTime.
+    const CERT_GENERALIZED_TIME: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBazCCARCgAwIBAgIBAjAKBggqhkjOPQQDAjATMREwDwYDVQQDDAhnZW4udGVz
+dDAgFw0yNTAxMDEwMDAwMDBaGA8yMDYwMDEwMTAwMDAwMFowEzERMA8GA1UEAwwI
+Z2VuLnRlc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARkHZ2J3kfOFBbGDJYg
+EJWUXdZWLZWFNtdZSQhPll26SYZ8BW04+L20JklTIJ77HXOWBi4CX4CPl94LRE45
+51Yxo1MwUTAdBgNVHQ4EFgQUzSf1FbzOZbWhpqWAzJ2H+6QVhakwHwYDVR0jBBgw
+FoAUzSf1FbzOZbWhpqWAzJ2H+6QVhakwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjO
+PQQDAgNJADBGAiEAzM3OcgxUqUl0ge481/1uoKEXtS7wl1apknskL6Yefy8CIQD+
+BZ0LgR1+XU1/9u7cVqM4QaYoaGGTxzdKv5ia0jpqzQ==
+-----END CERTIFICATE-----";
+
+    fn der(pem: &str) -> Vec<u8> {
+        CertificateDer::from_pem_slice(pem.as_bytes()).unwrap().to_vec()
+    }
+
+    /// An empty database with the current schema.
+    fn test_db() -> Connection {
+        let mut db = Connection::open_in_memory().unwrap();
+        migrate(&mut db, &Config::default()).unwrap();
+        db
+    }
+
+    #[test]
+    fn new_targets_render_pending_not_down() {
+        let db = test_db();
+        let cfg: Config = toml::from_str(r#"
+            [[hosts]]
+            addr  = "192.0.2.1"
+            label = "New host"
+
+            [[services]]
+            label  = "New service"
+            check  = "tcp"
+            target = "192.0.2.1:443"
+        "#).unwrap();
+
+        let html = render_host(&db, &cfg, &cfg.hosts[0], None, None, &HashMap::new(), false, None);
+        assert!(html.contains("tier-pending"), "{html}");
+        assert!(!html.contains("tier-down"), "{html}");
+
+        let svcs: Vec<&Service> = cfg.services.iter().collect();
+        let html = render_service_card(&db, &cfg, "Services", &svcs, 0, false, None, &HashMap::new(), &HashMap::new(), false, false, None);
+        assert!(html.contains("tier-pending"), "{html}");
+        assert!(!html.contains("tier-down"), "{html}");
+    }
+
+    #[test]
+    fn ack_does_not_outlive_its_outage() {
+        let db = test_db();
+        let cfg: Config = toml::from_str(r#"
+            [[hosts]]
+            addr  = "192.0.2.1"
+            label = "NAS"
+
+            [[acks]]
+            key   = "192.0.2.1"
+            since = "2026-10-16T09:00:00+00:00"
+            note  = "ISP ticket #123"
+        "#).unwrap();
+        let check = |hh_mm: &str, status: &str| {
+            let ts = chrono::DateTime::parse_from_rfc3339(&format!("2026-10-16T{hh_mm}:00+00:00")).unwrap();
+            db.execute(
+                "INSERT INTO ping_results (host, timestamp, status) VALUES ('192.0.2.1', ?1, ?2)",
+                params![ts.with_timezone(&Local).to_rfc3339(), status],
+            )
+            .unwrap();
+        };
+        let ack = |db: &Connection| query_down_since(db, "192.0.2.1").and_then(|since| query_ack(db, &cfg, "192.0.2.1", &since));
+
+        check("08:55", "UP");
+        check("09:05", "DOWN");
+        check("09:10", "DOWN");
+        assert_eq!(ack(&db), Some("ISP ticket #123"));
+
+        // Recovered, then down again: a new outage the ack doesn't cover.
+        check("09:20", "UP");
+        assert_eq!(query_down_since(&db, "192.0.2.1"), None);
+        check("09:30", "DOWN");
+        assert_eq!(ack(&db), None);
+    }
+
+    #[test]
+    fn der_tlv_lengths() {
+        assert_eq!(der_tlv(&[0x04, 0x02, 1, 2, 3]), Some((0x04, &[1, 2][..], &[3][..])));
+        assert_eq!(der_tlv(&[0x04, 0x81, 0x02, 1, 2]), Some((0x04, &[1, 2][..], &[][..])));
+        assert_eq!(der_tlv(&[0x04, 0x03, 1, 2]), None);           // runs past the end
+        assert_eq!(der_tlv(&[0x04, 0x80, 1]), None);              // indefinite length
+        assert_eq!(der_tlv(&[0x04, 0x85, 0, 0, 0, 0, 1, 1]), None); // length of length > 4
+        assert_eq!(der_tlv(&[0x04]), None);
+        assert_eq!(der_tlv(&[]), None);
+    }
+
+    #[test]
+    fn cert_not_after_utc_time() {
+        assert_eq!(cert_not_after(&der(CERT_UTC_TIME)), Some(2_051_222_400));
+    }
+
+    #[test]
+    fn cert_not_after_generalized_time() {
+        assert_eq!(cert_not_after(&der(CERT_GENERALIZED_TIME)), Some(2_840_140_800));
+    }
+
+    #[test]
+    fn cert_not_after_rejects_truncated_and_garbage() {
+        let cert = der(CERT_UTC_TIME);
+        for len in 0..cert.len() {
+            assert_eq!(cert_not_after(&cert[..len]), None, "truncated to {len} bytes");
+        }
+        assert_eq!(cert_not_after(&[0xff; 64]), None);
+        assert_eq!(cert_not_after(&[0x30, 0x03, 0x30, 0x01, 0x00]), None);
+    }
+
+    /// A reply to `dns_query("example.com", 1)` with header flags `flags` and
+    /// `answers` as (owner name, type, rdata).
+    fn dns_reply(flags: [u8; 2], answers: &[(&[u8], u16, &[u8])]) -> Vec<u8> {
+        let mut buf = dns_query("example.com", 1);
+        buf[2..4].copy_from_slice(&flags);
+        buf[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+        for (name, qtype, rdata) in answers {
+            buf.extend_from_slice(name);
+            buf.extend_from_slice(&qtype.to_be_bytes());
+            buf.extend_from_slice(&[0x00, 0x01, 0, 0, 0x0e, 0x10]); // class IN, TTL 3600
+            buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            buf.extend_from_slice(rdata);
+        }
+        buf
+    }
+
+    const NOERROR: [u8; 2] = [0x81, 0x80];
+    const QNAME: &[u8] = &[0xC0, 0x0C];  // pointer to the question's name
+    const WWW: &[u8] = b"\x03www\x07example\x03com\x00";
+
+    #[test]
+    fn dns_compressed_answer() {
+        assert_eq!(dns_verify(&dns_reply(NOERROR, &[(QNAME, 1, &[192, 0, 2, 1])]), 1, None), Ok(()));
+    }
+
+    #[test]
+    fn dns_cname_then_a() {
+        let reply = dns_reply(NOERROR, &[(QNAME, 5, WWW), (WWW, 1, &[192, 0, 2, 1])]);
+        assert_eq!(dns_verify(&reply, 1, None), Ok(()));
+        let cname_only = dns_reply(NOERROR, &[(QNAME, 5, WWW)]);
+        assert_eq!(dns_verify(&cname_only, 1, None), Err("no A record in answer".into()));
+    }
+
+    #[test]
+    fn dns_error_rcodes() {
+        assert_eq!(dns_verify(&dns_reply([0x81, 0x83], &[]), 1, None), Err("NXDOMAIN".into()));
+        assert_eq!(dns_verify(&dns_reply([0x81, 0x82], &[]), 1, None), Err("SERVFAIL".into()));
+    }
+
+    #[test]
+    fn dns_truncated_flag_is_down() {
+        let reply = dns_reply([0x83, 0x80], &[(QNAME, 1, &[192, 0, 2, 1])]);
+        assert_eq!(dns_verify(&reply, 1, None), Err("truncated response".into()));
+    }
+
+    #[test]
+    fn dns_truncated_buffer_is_malformed() {
+        let reply = dns_reply(NOERROR, &[(QNAME, 5, WWW), (WWW, 1, &[192, 0, 2, 1])]);
+        for len in 0..reply.len() {
+            assert!(dns_verify(&reply[..len], 1, None).is_err(), "truncated to {len} bytes");
+        }
+    }
+
+    #[test]
+    fn dns_expect_answer() {
+        let reply = dns_reply(NOERROR, &[(QNAME, 1, &[192, 0, 2, 1]), (QNAME, 1, &[192, 0, 2, 2])]);
+        assert_eq!(dns_verify(&reply, 1, Some("192.0.2.2".parse().unwrap())), Ok(()));
+        assert_eq!(
+            dns_verify(&reply, 1, Some("192.0.2.9".parse().unwrap())),
+            Err("wrong answer: got 192.0.2.1, 192.0.2.2, expected 192.0.2.9".into()),
+        );
+    }
+
+    #[test]
+    fn dns_sinkhole_answer_is_unusable() {
+        let reply = dns_reply(NOERROR, &[(QNAME, 1, &[0, 0, 0, 0])]);
+        assert_eq!(dns_verify(&reply, 1, None), Err("unusable answer 0.0.0.0".into()));
+    }
+
+    #[test]
+    fn dns_skip_name_is_bounded() {
+        let mut long = [63u8; 64 * 5].to_vec();  // five 63-byte labels, then the root: 321 octets
+        long.push(0);
+        assert_eq!(dns_skip_name(&long, 0), None);
+        assert_eq!(dns_skip_name(WWW, 0), Some(WWW.len()));
+        assert_eq!(dns_skip_name(&WWW[..5], 0), None);
+    }
+}
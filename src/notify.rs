@@ -0,0 +1,267 @@
+//! Outage notifications (Telegram, ntfy, Pushover, email, webhook, Discord, Slack). The poll loop hands every
+//! UP↔DOWN transition to `dispatch`, which fans it out to each configured channel
+//! on its own task so a slow or failing API never delays the next round. Email
+//! waits for `alert_after_n` DOWN checks, so the poll loop sends it via `dispatch_email`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use pi_glass::{fmt_duration, fmt_latency, html_escape, send_mail, Config, TelegramConfig, NOTIFY_CHANNELS};
+
+#[derive(Clone)]
+pub struct Transition {
+    pub key: String,
+    pub label: String,
+    pub status: &'static str,
+    pub previous: &'static str,
+    /// How long the target had been in `previous`.
+    pub lasted: Duration,
+    /// The check that completed the transition.
+    pub timestamp: String,
+    pub latency_ms: Option<f64>,
+    /// Shown by the Discord and Slack messages.
+    pub uptime_1h: Option<f64>,
+    /// A DOWN in an outage acknowledged in config (`[[acks]]`): never sent.
+    pub acked: bool,
+}
+
+/// The `[webhook]` body.
+#[derive(Serialize)]
+struct WebhookEvent<'a> {
+    target: &'a str,
+    kind: &'static str,
+    old_status: &'a str,
+    new_status: &'a str,
+    latency_ms: Option<f64>,
+    timestamp: &'a str,
+}
+
+impl Transition {
+    /// "🔴 NAS is DOWN (was UP 6m)", "🟢 NAS is UP (was DOWN 6m) · 4ms"
+    pub fn message(&self) -> String {
+        let icon = if self.status == "UP" { "🟢" } else { "🔴" };
+        let mut text = format!("{icon} {} is {} (was {} {})", self.label, self.status, self.previous, fmt_duration(self.lasted));
+        if self.latency_ms.is_some() {
+            text.push_str(&format!(" · {}", fmt_latency(self.latency_ms)));
+        }
+        text
+    }
+}
+
+/// Sends `t` to every configured channel the target routes to (`channels`).
+/// Must be called from within a tokio runtime.
+/// Channels are independent: each gets its own request and task.
+pub fn dispatch(cfg: &Config, client: &reqwest::Client, t: &Transition) {
+    let down = t.status == "DOWN";
+    // Acknowledged outages are already known — stay quiet until they recover.
+    if t.acked {
+        return;
+    }
+    let text = t.message();
+    for channel in NOTIFY_CHANNELS.iter().filter(|c| **c != "email" && cfg.routes_to(&t.key, c)) {
+        // Delivery runs on its own task; nothing here waits for it.
+        let _ = match *channel {
+            "webhook" => send_webhook(cfg, client, t),
+            "discord" | "slack" => send(cfg, client, channel, &chat_message(t), down),
+            _ => send(cfg, client, channel, &text, down),
+        };
+    }
+}
+
+/// Mails `t` as "DOWN: Gateway" if the target routes to `email`; ack-filtered as
+/// `dispatch` is. The poll loop calls this once a DOWN has lasted `alert_after_n`
+/// checks, and for the recovery that follows.
+pub fn dispatch_email(cfg: &Config, t: &Transition) {
+    if t.acked || !cfg.routes_to(&t.key, "email") {
+        return;
+    }
+    send_email(cfg, &format!("{}: {}", t.status, t.label), &t.message());
+}
+
+/// One message per channel listing the transitions held back during quiet hours,
+/// routed and ack-filtered as `dispatch` would have. Sent at normal priority.
+pub fn dispatch_summary(cfg: &Config, client: &reqwest::Client, held: &[Transition]) {
+    for channel in NOTIFY_CHANNELS {
+        let held = held.iter()
+            .filter(|t| cfg.routes_to(&t.key, channel))
+            .filter(|t| !t.acked);
+        // Automation wants the events themselves, not a digest.
+        if *channel == "webhook" {
+            held.for_each(|t| { send_webhook(cfg, client, t); });
+            continue;
+        }
+        let lines: Vec<String> = held.map(Transition::message).collect();
+        if !lines.is_empty() {
+            let text = format!("🌙 During quiet hours:\n{}", lines.join("\n"));
+            send(cfg, client, channel, &text, false);
+        }
+    }
+}
+
+/// `--test-notify`: one test message to every configured channel, ignoring routing;
+/// returns once each has been delivered or has failed (errors are logged).
+pub async fn send_test(cfg: &Config, client: &reqwest::Client) -> usize {
+    let t = Transition {
+        key: "svc:pi-glass test".to_string(),
+        label: "pi-glass test".to_string(),
+        status: "UP",
+        previous: "UP",
+        lasted: Duration::ZERO,
+        timestamp: chrono::Local::now().to_rfc3339(),
+        latency_ms: None,
+        uptime_1h: None,
+        acked: false,
+    };
+    let text = format!("✅ Test notification from {}", cfg.name);
+    let mut sent = Vec::new();
+    for channel in NOTIFY_CHANNELS.iter().filter(|c| cfg.has_channel(c)) {
+        println!("Sending a test notification via {channel}");
+        sent.extend(match *channel {
+            "webhook" => send_webhook(cfg, client, &t),
+            _ => send(cfg, client, channel, &text, false),
+        });
+    }
+    let count = sent.len();
+    for task in sent {
+        let _ = task.await;
+    }
+    count
+}
+
+/// Posts `text` to `channel` if it's configured; `urgent` raises its priority.
+fn send(cfg: &Config, client: &reqwest::Client, channel: &'static str, text: &str, urgent: bool) -> Option<JoinHandle<()>> {
+    match channel {
+        "telegram" => cfg.telegram.as_ref().map(|tg| {
+            // HTML mode so a label can't be taken for Markdown; escaped to match.
+            let req = client.post(telegram_url(tg))
+                .form(&[("chat_id", tg.chat_id.as_str()), ("text", &html_escape(text)), ("parse_mode", "HTML")]);
+            spawn_send(channel, req)
+        }),
+        "ntfy" => cfg.ntfy.as_ref().map(|n| {
+            // ntfy priorities: 5 = urgent (DOWN), 3 = default (recovery)
+            let mut req = client.post(format!("{}/{}", n.url.trim_end_matches('/'), n.topic))
+                .header("Title", cfg.name.as_str())
+                .header("Priority", if urgent { "5" } else { "3" })
+                .header("Tags", if urgent { "red_circle" } else { "green_circle" })
+                .body(text.to_string());
+            if let Some(token) = &n.token {
+                req = req.bearer_auth(token);
+            }
+            spawn_send(channel, req)
+        }),
+        "pushover" => cfg.pushover.as_ref().map(|p| {
+            // Pushover priorities: 1 = high (DOWN), 0 = normal (recovery)
+            let req = client.post("https://api.pushover.net/1/messages.json")
+                .form(&[
+                    ("token", p.token.as_str()),
+                    ("user", p.user.as_str()),
+                    ("title", cfg.name.as_str()),
+                    ("message", text),
+                    ("priority", if urgent { "1" } else { "0" }),
+                ]);
+            spawn_send(channel, req)
+        }),
+        "email" => send_email(cfg, &format!("{}: notifications", cfg.name), text),
+        "discord" => cfg.discord.as_ref().map(|d| {
+            spawn_spaced(channel, client.post(&d.webhook_url).header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(serde_json::json!({ "content": text }).to_string()))
+        }),
+        "slack" => cfg.slack.as_ref().map(|s| {
+            spawn_spaced(channel, client.post(&s.webhook_url).header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(serde_json::json!({ "text": text }).to_string()))
+        }),
+        _ => None,
+    }
+}
+
+/// "🔴 NAS is DOWN (was UP 6m) · 1h uptime 98.3%"
+fn chat_message(t: &Transition) -> String {
+    match t.uptime_1h {
+        Some(pct) => format!("{} · 1h uptime {pct:.1}%", t.message()),
+        None => t.message(),
+    }
+}
+
+/// Discord and Slack webhooks allow about one message a second; when many targets
+/// change at once their messages go out that far apart instead of being rejected.
+const CHAT_SPACING: Duration = Duration::from_secs(1);
+
+fn spawn_spaced(channel: &'static str, req: reqwest::RequestBuilder) -> JoinHandle<()> {
+    static NEXT: Mutex<Option<HashMap<&'static str, Instant>>> = Mutex::new(None);
+    let at = {
+        let mut next = NEXT.lock().unwrap();
+        let slot = next.get_or_insert_with(HashMap::new).entry(channel).or_insert_with(Instant::now);
+        let at = (*slot).max(Instant::now());
+        *slot = at + CHAT_SPACING;
+        at
+    };
+    tokio::spawn(async move {
+        tokio::time::sleep_until(at.into()).await;
+        log_result(channel, req.send().await).await;
+    })
+}
+
+/// Mails `text` on its own task if `[mailer] alert_on_change` is set.
+fn send_email(cfg: &Config, subject: &str, text: &str) -> Option<JoinHandle<()>> {
+    let mailer = cfg.mailer.as_ref().filter(|m| m.alert_on_change)?;
+    let (mailer, subject) = (mailer.clone(), subject.to_string());
+    let html = format!("<p>{}</p>", html_escape(text).replace('\n', "<br>"));
+    Some(tokio::spawn(async move {
+        if let Err(e) = send_mail(&mailer, &subject, &html, None).await {
+            warn!("email error: {e}");
+        }
+    }))
+}
+
+/// POSTs `t` as JSON to `[webhook] url` on its own task, retrying twice (after 2s,
+/// then 4s) if the request fails or the endpoint answers with an error.
+fn send_webhook(cfg: &Config, client: &reqwest::Client, t: &Transition) -> Option<JoinHandle<()>> {
+    let hook = cfg.webhook.as_ref()?;
+    let body = serde_json::to_string(&WebhookEvent {
+        target: &t.label,
+        kind: if t.key.starts_with("svc:") { "service" } else { "host" },
+        old_status: t.previous,
+        new_status: t.status,
+        latency_ms: t.latency_ms,
+        timestamp: &t.timestamp,
+    }).unwrap();
+    let req = client.post(&hook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body);
+    Some(tokio::spawn(async move {
+        for delay in [0, 2, 4] {
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            let Some(attempt) = req.try_clone() else { return };
+            match attempt.send().await {
+                Ok(resp) if resp.status().is_success() => return,
+                res => log_result("webhook", res).await,
+            }
+        }
+    }))
+}
+
+fn spawn_send(channel: &'static str, req: reqwest::RequestBuilder) -> JoinHandle<()> {
+    tokio::spawn(async move { log_result(channel, req.send().await).await })
+}
+
+fn telegram_url(tg: &TelegramConfig) -> String {
+    format!("https://api.telegram.org/bot{}/sendMessage", tg.bot_token)
+}
+
+async fn log_result(channel: &str, res: Result<reqwest::Response, reqwest::Error>) {
+    match res {
+        Ok(resp) if resp.status().is_success() => {}
+        Ok(resp) => {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            warn!("{channel} error {status}: {body}");
+        }
+        // without_url: the Telegram URL embeds the bot token
+        Err(e) => warn!("{channel} request failed: {}", e.without_url()),
+    }
+}